@@ -0,0 +1,122 @@
+use crate::engine::{Engine, Score};
+use crate::pgn::movetree::MoveTree;
+use shakmaty::{Chess, Position, Setup};
+
+/// A move of the student which the engine thinks is a mistake
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Finding {
+    /// The line up to and including the move, see `Variation::id`
+    pub line: String,
+    /// What the move gives away compared to the engine's move, in centipawns
+    pub loss: i32,
+    /// The engine's move instead, in SAN
+    pub best_move: String,
+    /// The score after the engine's move, for the student
+    pub best_score: Score,
+    /// The score after the student's move, for the student
+    pub score: Score
+}
+
+impl Finding {
+    /// A NAG and a comment for `MoveTree::to_pgn`
+    pub fn annotation(&self) -> String {
+        format!("$2 {{Verliert {:.2}, besser {} ({})}}", self.loss as f64 / 100.0, self.best_move, self.best_score)
+    }
+}
+
+/// A move of the repertoire which cannot be played in its position, e.g. a typo
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IllegalMove {
+    /// The line up to and including the move, see `Variation::id`
+    pub line: String
+}
+
+impl std::fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Ungültiger Zug in {}", crate::trainer::format_moves(&self.line))
+    }
+}
+
+/// Parses the SAN and plays it
+fn play(pos: &mut Chess, san: &str) -> Option<()> {
+    let m = san.parse::<shakmaty::san::San>().ok()?.to_move(pos).ok()?;
+    pos.play_unchecked(&m);
+    Some(())
+}
+
+/// The score for the side which played the last move, also at the end of the game
+async fn score_after(engine: &dyn Engine, pos: &Chess) -> Score {
+    match engine.analyse(pos).await {
+        Some(analysis) => analysis.score.flip(),
+        None if pos.is_checkmate() => Score::Mate(0),
+        None => Score::Centipawns(0)
+    }
+}
+
+/// Evaluates every move of the student in the tree and returns the ones which lose more than
+/// `threshold` centipawns against the engine's best move, in the order of the tree. Fails at the
+/// first move which cannot be played
+pub async fn audit(tree: &MoveTree<'_>, student: shakmaty::Color, engine: &dyn Engine, threshold: i32) -> Result<Vec<Finding>, IllegalMove> {
+    // Positions with several moves of the student are only analysed once
+    let mut analyses = std::collections::HashMap::new();
+    let mut findings = Vec::new();
+
+    for line in tree.lines() {
+        let illegal = || IllegalMove { line: line.join(" ") };
+        let (last, before) = line.split_last().unwrap();
+        // The lines before this one end with each of these moves, so they were checked already
+        let mut pos = Chess::default();
+        for san in before {
+            play(&mut pos, san).ok_or_else(illegal)?;
+        }
+        if pos.turn() != student {
+            // The opponent's move has to be legal for the lines after it
+            play(&mut pos.clone(), last).ok_or_else(illegal)?;
+            continue;
+        }
+
+        let fen = shakmaty::fen::fen(&pos);
+        if !analyses.contains_key(&fen) {
+            let analysis = engine.analyse(&pos).await;
+            analyses.insert(fen.clone(), analysis);
+        }
+        let best = match &analyses[&fen] {
+            Some(best) => best,
+            None => continue
+        };
+
+        let best_move = shakmaty::san::San::from_move(&pos, &best.best_move).to_string();
+        play(&mut pos, last).ok_or_else(illegal)?;
+        let score = score_after(engine, &pos).await;
+
+        let loss = best.score.centipawns() - score.centipawns();
+        if loss > threshold {
+            findings.push(Finding { line: line.join(" "), loss, best_move, best_score: best.score, score });
+        }
+    }
+
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{audit, IllegalMove};
+    use crate::engine::AlphaBeta;
+    use crate::pgn::movetree::MoveTree;
+
+    #[test]
+    fn flags_blunders() {
+        let mut tree = MoveTree::new();
+        tree.add_pgn("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 (3... g6) 4. Qxf7#");
+
+        let findings = crate::util::run_local(audit(&tree, shakmaty::Color::Black, &AlphaBeta::new(2), 100)).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].line, "e4 e5 Qh5 Nc6 Bc4 Nf6");
+        assert_eq!(findings[0].score, crate::engine::Score::Mate(-1));
+
+        let mut tree = MoveTree::new();
+        tree.add_pgn("1. e4 e5 2. Ke3 Nc6");
+        let result = crate::util::run_local(audit(&tree, shakmaty::Color::Black, &AlphaBeta::new(1), 100));
+        assert_eq!(result, Err(IllegalMove { line: "e4 e5 Ke3".to_string() }));
+    }
+}
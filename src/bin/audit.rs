@@ -0,0 +1,76 @@
+use chess_trainer::{audit, engine, pgn, trainer, util};
+
+const USAGE: &str = "Aufruf: audit <repertoire.pgn> [--engine PFAD] [--depth N] [--threshold 50] [--color white|black] [--pgn ausgabe.pgn]";
+
+/// Checks the moves of a repertoire with an engine and lists the ones which lose too much.
+/// With `--pgn`, the repertoire is also written with the findings as NAGs and comments
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let arg = |name: &str| args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1)).cloned();
+
+    let path = match args.first().filter(|path| !path.starts_with("--")) {
+        Some(path) => path.clone(),
+        None => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let depth = arg("--depth").and_then(|depth| depth.parse().ok());
+    let threshold = arg("--threshold").and_then(|threshold| threshold.parse().ok()).unwrap_or(50);
+
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            std::process::exit(1);
+        }
+    };
+    let mut tree = pgn::movetree::MoveTree::new();
+    tree.add_pgn(&source);
+
+    // The side of the repertoire, as in the trainer
+    let student = match arg("--color").as_deref() {
+        Some("white") => shakmaty::Color::White,
+        Some("black") => shakmaty::Color::Black,
+        _ => tree.orientation().unwrap_or(shakmaty::Color::White)
+    };
+
+    let findings = util::run_local(async {
+        let engine: Box<dyn engine::Engine> = match arg("--engine") {
+            Some(engine_path) => {
+                let command = std::process::Command::new(&engine_path);
+                match engine::uci::UciEngine::start(command, depth.unwrap_or(12)).await {
+                    Ok(engine) => Box::new(engine),
+                    Err(error) => {
+                        eprintln!("{}: {}", engine_path, error);
+                        std::process::exit(1);
+                    }
+                }
+            },
+            None => Box::new(engine::AlphaBeta::new(depth.unwrap_or(3)))
+        };
+
+        audit::audit(&tree, student, &*engine, threshold).await
+    });
+    let findings = match findings {
+        Ok(findings) => findings,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            std::process::exit(1);
+        }
+    };
+
+    for finding in &findings {
+        println!("{}: verliert {:.2} ({}), besser {} ({})", trainer::format_moves(&finding.line),
+            finding.loss as f64 / 100.0, finding.score, finding.best_move, finding.best_score);
+    }
+    println!("{} Züge mit mehr als {:.2} Verlust", findings.len(), threshold as f64 / 100.0);
+
+    if let Some(output) = arg("--pgn") {
+        let annotations = findings.iter().map(|finding| (finding.line.clone(), finding.annotation())).collect();
+        if let Err(error) = std::fs::write(&output, tree.to_pgn(&annotations)) {
+            eprintln!("{}: {}", output, error);
+            std::process::exit(1);
+        }
+    }
+}
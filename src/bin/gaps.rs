@@ -0,0 +1,47 @@
+use chess_trainer::{explorer, pgn, trainer};
+
+const USAGE: &str = "Aufruf: gaps <repertoire.pgn> <partien.pgn> [--color white|black] [--limit 20]";
+
+/// Lists the replies of the opponent from a games database which the repertoire doesn't answer,
+/// the most frequent first, to know which lines to prepare next
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let arg = |name: &str| args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1)).cloned();
+
+    let paths: Vec<&String> = args.iter().take(2).filter(|path| !path.starts_with("--")).collect();
+    if paths.len() < 2 {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+    let limit = arg("--limit").and_then(|limit| limit.parse().ok()).unwrap_or(20);
+
+    let read = |path: &str| match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            std::process::exit(1);
+        }
+    };
+    let repertoire = read(paths[0]);
+    let games = read(paths[1]);
+
+    let mut tree = pgn::movetree::MoveTree::new();
+    tree.add_pgn(&repertoire);
+    let mut explorer = explorer::Explorer::new();
+    let import = explorer.add_pgn(&games);
+
+    // The side of the repertoire, as in the trainer
+    let student = match arg("--color").as_deref() {
+        Some("white") => shakmaty::Color::White,
+        Some("black") => shakmaty::Color::Black,
+        _ => tree.orientation().unwrap_or(shakmaty::Color::White)
+    };
+
+    let gaps = explorer.gaps(&tree, student);
+    for gap in gaps.iter().take(limit) {
+        let line = if gap.line.is_empty() { "Grundstellung".to_string() } else { trainer::format_moves(&gap.line) };
+        println!("{}: {} in {} von {} Partien ({:.0} %)", line, gap.reply, gap.games, gap.position_games,
+            100.0 * gap.games as f64 / gap.position_games as f64);
+    }
+    println!("{} fehlende Antworten in {} Partien ({} nicht lesbar)", gaps.len(), import.imported, import.skipped);
+}
@@ -0,0 +1,637 @@
+use chess_trainer::{components::board::{Arrow, Label}, coverage, engine, explorer, own_games, session, storage, trainer, util};
+use std::rc::Rc;
+use std::cell::RefCell;
+
+const HELP: &str = "Zug in SAN (Nf3) oder UCI (g1f3) eingeben. r = Neustart, n = Nächste Variante, e = Erkunden, a = ab hier trainieren, + = zum Repertoire hinzufügen, x = Prüfung, i = Sitzung, s = Seite wechseln, b/f = Zug zurück/vor, m = Modus wechseln, l = Lernmodus, h 1 2 3 = Hinweise nach Fehlern (Figur, Feld, Pfeil), t 10 = Sekunden pro Zug, d 3 = Weiterspielen gegen Engine (Tiefe), g datei.pgn = Partien importieren, o datei.pgn [Name] = eigene Partien prüfen, p 4 / p z = Start ab Halbzug / zufällig, z 42 = Seed, q = Beenden";
+
+/// Search depth of an engine given with `--engine`
+const UCI_DEPTH: u32 = 12;
+
+struct State {
+    board: shakmaty::Chess,
+    arrows: Vec<Arrow>,
+    labels: Vec<Label>,
+    highlights: Vec<shakmaty::Square>,
+    explore: bool,
+    exam: bool,
+    learning: bool,
+    hint_policy: trainer::HintPolicy,
+    time_limit: u32,
+    engine_depth: u32,
+    /// Given with `--engine PATH`, used instead of the built-in engine
+    uci_engine: Option<Rc<dyn engine::Engine>>,
+    countdown: Option<u32>,
+    start_point: trainer::StartPoint,
+    /// Given with `--seed N`
+    initial_seed: Option<u64>,
+    seed: u64,
+    reverse: bool,
+    mode: trainer::Mode,
+    progress: (usize, usize),
+    level: Option<trainer::Level>,
+    feedback: Option<trainer::Feedback>,
+    off_book: Option<String>,
+    explorer: Option<trainer::ExplorerMoves>,
+    /// The last import of games, and whether it was saved
+    import: Option<(explorer::Import, bool)>,
+    checked_games: Vec<own_games::CheckedGame>,
+    exam_result: Option<Vec<trainer::Deviation>>,
+    session: session::Session,
+    show_session: bool,
+    message: Option<&'static str>
+}
+
+#[derive(Clone)]
+struct UI {
+    state: Rc<RefCell<State>>,
+    user_move_channel: Rc<util::EventChannel<shakmaty::Move>>,
+    user_action_channel: Rc<util::EventChannel<trainer::UserAction>>
+}
+
+impl UI {
+    fn new(initial_seed: Option<u64>) -> Self {
+        UI {
+            state: Rc::new(RefCell::new(State {
+                board: Default::default(),
+                arrows: Vec::new(),
+                labels: Vec::new(),
+                highlights: Vec::new(),
+                explore: false,
+                exam: false,
+                learning: true,
+                hint_policy: Default::default(),
+                time_limit: 0,
+                engine_depth: 0,
+                uci_engine: None,
+                countdown: None,
+                start_point: Default::default(),
+                initial_seed,
+                seed: 0,
+                reverse: true,
+                mode: trainer::Mode::default(),
+                progress: (0, 0),
+                level: None,
+                feedback: None,
+                off_book: None,
+                explorer: None,
+                import: None,
+                checked_games: Vec::new(),
+                exam_result: None,
+                session: session::Session::new(),
+                show_session: false,
+                message: None
+            })),
+            user_move_channel: Rc::new(util::EventChannel::new()),
+            user_action_channel: Rc::new(util::EventChannel::new())
+        }
+    }
+
+    fn render(&self) {
+        use std::io::Write;
+
+        let state = self.state.borrow();
+        let mut out = String::new();
+        out += "\x1b[2J\x1b[H";
+        out += if state.learning { "Lernmodus (Pfeile anzeigen)" } else { "Übungsmodus (ohne Pfeile)" };
+        if state.explore {
+            out += " - Erkunden";
+        }
+        if state.exam {
+            out += " - Prüfung";
+        }
+        match state.mode {
+            trainer::Mode::Repetition => out += " - Wiederholen",
+            trainer::Mode::Sequential => out += " - Der Reihe nach",
+            trainer::Mode::Shuffled => out += " - Zufällige Variante",
+            trainer::Mode::WeakestFirst => out += " - Schwächste zuerst",
+            trainer::Mode::Weighted => out += " - Nach Häufigkeit",
+            trainer::Mode::Coverage(order) => {
+                out += if order == coverage::Order::Tree { " - Alle Varianten" } else { " - Alle Varianten (kurze zuerst)" };
+                out += &format!(" ({} / {} Varianten)", state.progress.0, state.progress.1);
+            },
+            trainer::Mode::Levels => out += " - Level",
+            trainer::Mode::Review => out += &format!(" - Fehler wiederholen ({} offen)", state.progress.1),
+            trainer::Mode::Random => out += " - Zufällige Antworten"
+        }
+        out += &format!(" - Seed {}", state.seed);
+        if let Some(level) = &state.level {
+            out += &format!("\n\nLevel {} / {}\n", level.number, level.count);
+            for (moves, mastered) in &level.lines {
+                out += if *mastered { "  ✓ " } else { "    " };
+                out += moves;
+                out += "\n";
+            }
+        }
+        out += "\n\n";
+
+        let ranks: Vec<shakmaty::Rank> = if state.reverse {
+            (0..8).map(shakmaty::Rank::new).collect()
+        } else {
+            (0..8).rev().map(shakmaty::Rank::new).collect()
+        };
+        let files: Vec<shakmaty::File> = if state.reverse {
+            (0..8).rev().map(shakmaty::File::new).collect()
+        } else {
+            (0..8).map(shakmaty::File::new).collect()
+        };
+
+        for &rank in &ranks {
+            out += &format!(" {} ", rank.char());
+            for &file in &files {
+                let square = shakmaty::Square::from_coords(file, rank);
+                out += square_color(square, &state.arrows, &state.highlights);
+                out += " ";
+                out.push(match shakmaty::Setup::board(&state.board).piece_at(square) {
+                    Some(piece) => piece_symbol(piece),
+                    None => ' '
+                });
+                out += " \x1b[0m";
+            }
+            out += "\n";
+        }
+
+        out += "   ";
+        for &file in &files {
+            out += &format!(" {} ", file.char());
+        }
+        out += "\n\n";
+
+        if !state.arrows.is_empty() {
+            let arrows: Vec<String> = state.arrows.iter().map(|Arrow(from, to, _)| {
+                match state.labels.iter().find(|Label(square, _)| square == to) {
+                    Some(Label(_, text)) => format!("{}→{} ({})", from, to, text),
+                    None => format!("{}→{}", from, to)
+                }
+            }).collect();
+            out += &arrows.join("  ");
+            out += "\n";
+        }
+
+        if !state.session.lines().is_empty() {
+            let seconds = |record: &session::LineRecord| format!("{:.1} s", record.time_per_move() / 1000.0);
+            let total = state.session.total();
+            out += &format!("Sitzung: {} Varianten, {} beendet, {} von {} Zügen auf Anhieb richtig, {} Hinweise, {} pro Zug\n",
+                state.session.lines().len(), state.session.completed(), total.first_try, total.moves, total.hints, seconds(&total));
+
+            if state.show_session {
+                for record in state.session.lines() {
+                    out += &format!("  {} {}: {} Züge, {} auf Anhieb, {} Hinweise, {} pro Zug\n",
+                        if record.completed { "✓" } else { " " }, trainer::format_moves(&record.line),
+                        record.moves, record.first_try, record.hints, seconds(record));
+                }
+            }
+        }
+
+        if let Some(deviations) = &state.exam_result {
+            out += &format!("Prüfung beendet, {} Fehler\n", deviations.len());
+            for deviation in deviations {
+                let san = |m| shakmaty::san::San::from_move(&deviation.position, m).to_string();
+                out += &format!("  {}: erwartet {}, gespielt {}\n",
+                    shakmaty::fen::fen(&deviation.position), san(&deviation.expected), san(&deviation.played));
+            }
+        }
+
+        if let Some(seconds) = state.countdown {
+            out += &format!("Noch {} s\n", seconds);
+        }
+
+        match &state.feedback {
+            Some(trainer::Feedback::NotPreferred(main_move)) => out += &format!("Gut, aber der Hauptzug ist {}\n", main_move),
+            Some(trainer::Feedback::EngineMove(best_move, score)) => out += &format!("Die Engine spielt {} ({})\n", best_move, score),
+            Some(trainer::Feedback::Evaluation(score)) => out += &format!("Bewertung: {}\n", score),
+            None => {}
+        }
+
+        if let Some(line) = &state.off_book {
+            out += &format!("Nicht im Repertoire: {} (+ = hinzufügen)\n", line);
+        }
+
+        if let Some((import, saved)) = state.import {
+            out += &format!("{} Partien importiert, {} nicht lesbar", import.imported, import.skipped);
+            out += if saved { "\n" } else { " (nicht gespeichert)\n" };
+        }
+
+        if let Some(explorer) = &state.explorer {
+            out += &format!("Partien ({} importiert):\n", explorer.games);
+            for (san, statistics) in &explorer.moves {
+                out += &format!("  {:<8} {:>6}", san, statistics.games);
+                if statistics.games > 0 {
+                    out += &format!(" {:>4.0} %", 100.0 * statistics.score(explorer.turn));
+                }
+                if let Some(rating) = statistics.average_rating() {
+                    out += &format!("  Ø {}", rating);
+                }
+                out += "\n";
+            }
+        }
+
+        if !state.checked_games.is_empty() {
+            out += &format!("{} eigene Partien geprüft:\n", state.checked_games.len());
+            for game in &state.checked_games {
+                out += &format!("  {}\n", game.describe());
+            }
+        }
+
+        if let Some(message) = state.message {
+            out += message;
+            out += "\n";
+        }
+
+        out += HELP;
+        out += "\n> ";
+
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(out.as_bytes());
+        let _ = stdout.flush();
+    }
+
+    fn set_message(&self, message: Option<&'static str>) {
+        self.state.borrow_mut().message = message;
+    }
+
+    fn handle_input(&self, line: &str) -> bool {
+        self.set_message(None);
+
+        match line {
+            "q" => return false,
+            "r" => self.user_action_channel.send(trainer::UserAction::Restart),
+            "n" => self.user_action_channel.send(trainer::UserAction::NextLevel),
+            "e" => self.user_action_channel.send(trainer::UserAction::ToggleExplore),
+            "a" => self.user_action_channel.send(trainer::UserAction::TrainFromHere),
+            "+" => self.user_action_channel.send(trainer::UserAction::AddToRepertoire),
+            "x" => self.user_action_channel.send(trainer::UserAction::ToggleExam),
+            "s" => self.user_action_channel.send(trainer::UserAction::SwitchSide),
+            "b" => self.user_action_channel.send(trainer::UserAction::StepBack),
+            "f" => self.user_action_channel.send(trainer::UserAction::StepForward),
+            "m" => {
+                let mode = match self.state.borrow().mode {
+                    trainer::Mode::Repetition => trainer::Mode::Sequential,
+                    trainer::Mode::Sequential => trainer::Mode::Shuffled,
+                    trainer::Mode::Shuffled => trainer::Mode::WeakestFirst,
+                    trainer::Mode::WeakestFirst => trainer::Mode::Weighted,
+                    trainer::Mode::Weighted => trainer::Mode::Coverage(coverage::Order::Tree),
+                    trainer::Mode::Coverage(coverage::Order::Tree) => trainer::Mode::Coverage(coverage::Order::ShortestFirst),
+                    trainer::Mode::Coverage(coverage::Order::ShortestFirst) => trainer::Mode::Levels,
+                    trainer::Mode::Levels => trainer::Mode::Review,
+                    trainer::Mode::Review => trainer::Mode::Random,
+                    trainer::Mode::Random => trainer::Mode::Repetition
+                };
+                self.user_action_channel.send(trainer::UserAction::SetMode(mode));
+            },
+            "i" => {
+                let mut state = self.state.borrow_mut();
+                state.show_session = !state.show_session;
+            },
+            "l" => {
+                let mut state = self.state.borrow_mut();
+                state.learning = !state.learning;
+            },
+            "" => {},
+            _ if line.starts_with("p ") => {
+                let start_point = match line[2..].trim() {
+                    "z" => Some(trainer::StartPoint::Random),
+                    "0" => Some(trainer::StartPoint::Beginning),
+                    ply => ply.parse().ok().map(trainer::StartPoint::Ply)
+                };
+                match start_point {
+                    Some(start_point) => self.state.borrow_mut().start_point = start_point,
+                    None => self.set_message(Some("Erwartet: p <Halbzug> oder p z"))
+                }
+            },
+            _ if line.starts_with("z ") => {
+                match line[2..].trim().parse() {
+                    Ok(seed) => self.user_action_channel.send(trainer::UserAction::Reseed(seed)),
+                    Err(_) => self.set_message(Some("Erwartet: z <Seed>"))
+                }
+            },
+            _ if line.starts_with("g ") => {
+                match std::fs::read_to_string(line[2..].trim()) {
+                    Ok(pgn) => self.user_action_channel.send(trainer::UserAction::ImportGames(pgn)),
+                    Err(_) => self.set_message(Some("Datei nicht lesbar"))
+                }
+            },
+            _ if line.starts_with("o ") => {
+                let mut words = line[2..].split_whitespace();
+                let path = words.next().unwrap_or_default();
+                let player = words.collect::<Vec<_>>().join(" ");
+                match std::fs::read_to_string(path) {
+                    Ok(pgn) => {
+                        let player = Some(player).filter(|player| !player.is_empty());
+                        self.user_action_channel.send(trainer::UserAction::CheckOwnGames(pgn, player));
+                    },
+                    Err(_) => self.set_message(Some("Datei nicht lesbar"))
+                }
+            },
+            _ if line.starts_with("d ") => {
+                match line[2..].trim().parse() {
+                    Ok(engine_depth) => self.state.borrow_mut().engine_depth = engine_depth,
+                    Err(_) => self.set_message(Some("Erwartet: d <Tiefe>"))
+                }
+            },
+            _ if line.starts_with("t ") => {
+                match line[2..].trim().parse() {
+                    Ok(time_limit) => self.state.borrow_mut().time_limit = time_limit,
+                    Err(_) => self.set_message(Some("Erwartet: t <Sekunden>"))
+                }
+            },
+            _ if line.starts_with("h ") => {
+                let thresholds: Vec<u32> = line[2..].split_whitespace().filter_map(|value| value.parse().ok()).collect();
+                match thresholds[..] {
+                    [piece, target, arrow] => {
+                        self.state.borrow_mut().hint_policy = trainer::HintPolicy { piece, target, arrow };
+                    },
+                    _ => self.set_message(Some("Erwartet: h <Figur> <Feld> <Pfeil>"))
+                }
+            },
+            _ => {
+                let m = parse_move(&self.state.borrow().board, line);
+                match m {
+                    Some(m) => self.user_move_channel.send(m),
+                    None => self.set_message(Some("Unbekannter oder ungültiger Zug"))
+                }
+            }
+        }
+
+        self.render();
+        true
+    }
+}
+
+fn parse_move(pos: &shakmaty::Chess, input: &str) -> Option<shakmaty::Move> {
+    if let Ok(san) = input.parse::<shakmaty::san::SanPlus>() {
+        if let Ok(m) = san.san.to_move(pos) {
+            return Some(m);
+        }
+    }
+
+    let uci: shakmaty::uci::Uci = input.parse().ok()?;
+    uci.to_move(pos).ok()
+}
+
+fn piece_symbol(piece: shakmaty::Piece) -> char {
+    match (piece.color, piece.role) {
+        (shakmaty::Color::White, shakmaty::Role::King) => '♔',
+        (shakmaty::Color::White, shakmaty::Role::Queen) => '♕',
+        (shakmaty::Color::White, shakmaty::Role::Rook) => '♖',
+        (shakmaty::Color::White, shakmaty::Role::Bishop) => '♗',
+        (shakmaty::Color::White, shakmaty::Role::Knight) => '♘',
+        (shakmaty::Color::White, shakmaty::Role::Pawn) => '♙',
+        (shakmaty::Color::Black, shakmaty::Role::King) => '♚',
+        (shakmaty::Color::Black, shakmaty::Role::Queen) => '♛',
+        (shakmaty::Color::Black, shakmaty::Role::Rook) => '♜',
+        (shakmaty::Color::Black, shakmaty::Role::Bishop) => '♝',
+        (shakmaty::Color::Black, shakmaty::Role::Knight) => '♞',
+        (shakmaty::Color::Black, shakmaty::Role::Pawn) => '♟'
+    }
+}
+
+/// ANSI background color of a square. Arrows are shown by highlighting their start and target squares
+fn square_color(square: shakmaty::Square, arrows: &[Arrow], highlights: &[shakmaty::Square]) -> &'static str {
+    if highlights.contains(&square) {
+        "\x1b[30;46m"
+    } else if arrows.iter().any(|Arrow(_, to, _)| *to == square) {
+        "\x1b[30;41m"
+    } else if arrows.iter().any(|Arrow(from, _, _)| *from == square) {
+        "\x1b[30;43m"
+    } else if square.is_light() {
+        "\x1b[30;47m"
+    } else {
+        "\x1b[30;42m"
+    }
+}
+
+impl trainer::UI for UI {
+    fn init(&self, pos: &'_ shakmaty::Chess, explore: bool, orientation: shakmaty::Color) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.board = pos.clone();
+            state.arrows = Vec::new();
+            state.labels = Vec::new();
+            state.highlights = Vec::new();
+            state.explore = explore;
+            state.reverse = orientation == shakmaty::Color::Black;
+            state.feedback = None;
+            state.exam_result = None;
+            state.countdown = None;
+        }
+        self.render();
+    }
+
+    fn play_move(&self, m: shakmaty::Move, arrows: Vec<Arrow>) {
+        {
+            use shakmaty::Position;
+            let mut state = self.state.borrow_mut();
+            state.board.play_unchecked(&m);
+            state.arrows = arrows;
+            state.labels = Vec::new();
+            state.highlights = Vec::new();
+        }
+        self.render();
+    }
+
+    fn set_explore(&self, explore: bool) {
+        self.state.borrow_mut().explore = explore;
+        self.render();
+    }
+
+    fn set_exam(&self, exam: bool) {
+        self.state.borrow_mut().exam = exam;
+        self.render();
+    }
+
+    fn update_arrows(&self, arrows: Vec<Arrow>) {
+        self.state.borrow_mut().arrows = arrows;
+        self.render();
+    }
+
+    fn update_labels(&self, labels: Vec<Label>) {
+        self.state.borrow_mut().labels = labels;
+        self.render();
+    }
+
+    fn update_highlights(&self, squares: Vec<shakmaty::Square>) {
+        self.state.borrow_mut().highlights = squares;
+        self.render();
+    }
+
+    fn shake(&self) {
+        self.set_message(Some("\x07Falscher Zug"));
+        self.render();
+    }
+
+    fn get_user_move(&self) -> util::DynFuture<shakmaty::Move> {
+        self.user_move_channel.receive()
+    }
+
+    fn wait_for_user_action(&self) -> util::DynFuture<trainer::UserAction> {
+        self.user_action_channel.receive()
+    }
+
+    fn show_progress(&self, mode: trainer::Mode, mastered: usize, total: usize) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.mode = mode;
+            state.progress = (mastered, total);
+        }
+        self.render();
+    }
+
+    fn show_level(&self, level: Option<trainer::Level>) {
+        self.state.borrow_mut().level = level;
+        self.render();
+    }
+
+    fn show_feedback(&self, feedback: Option<trainer::Feedback>) {
+        self.state.borrow_mut().feedback = feedback;
+        self.render();
+    }
+
+    fn show_exam_result(&self, deviations: Vec<trainer::Deviation>) {
+        self.state.borrow_mut().exam_result = Some(deviations);
+        self.render();
+    }
+
+    fn show_session(&self, session: session::Session) {
+        self.state.borrow_mut().session = session;
+        self.render();
+    }
+
+    fn show_off_book(&self, line: Option<String>) {
+        self.state.borrow_mut().off_book = line;
+        self.render();
+    }
+
+    fn show_explorer(&self, moves: Option<trainer::ExplorerMoves>) {
+        self.state.borrow_mut().explorer = moves;
+        self.render();
+    }
+
+    fn show_import(&self, import: explorer::Import, saved: bool) {
+        self.state.borrow_mut().import = Some((import, saved));
+        self.render();
+    }
+
+    fn show_checked_games(&self, games: Vec<own_games::CheckedGame>) {
+        self.state.borrow_mut().checked_games = games;
+        self.render();
+    }
+
+    fn show_countdown(&self, seconds: Option<u32>) {
+        self.state.borrow_mut().countdown = seconds;
+        self.render();
+    }
+
+    fn start_point(&self) -> trainer::StartPoint {
+        self.state.borrow().start_point
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.state.borrow().initial_seed
+    }
+
+    fn show_seed(&self, seed: u64) {
+        self.state.borrow_mut().seed = seed;
+        self.render();
+    }
+
+    fn time_limit(&self) -> u32 {
+        self.state.borrow().time_limit
+    }
+
+    fn engine(&self) -> Option<Rc<dyn engine::Engine>> {
+        let state = self.state.borrow();
+        if let Some(engine) = &state.uci_engine {
+            return Some(engine.clone());
+        }
+
+        if state.engine_depth == 0 {
+            None
+        } else {
+            Some(Rc::new(engine::AlphaBeta::new(state.engine_depth)))
+        }
+    }
+
+    fn explore_engine(&self) -> Option<Rc<dyn engine::Engine>> {
+        self.engine()
+    }
+
+    fn show_hints(&self) -> bool {
+        self.state.borrow().learning
+    }
+
+    fn hint_policy(&self) -> trainer::HintPolicy {
+        self.state.borrow().hint_policy
+    }
+}
+
+/// `$XDG_DATA_HOME/chess-trainer`, or `~/.local/share/chess-trainer`, or the working directory without a home
+fn data_directory() -> std::path::PathBuf {
+    let data_home = std::env::var_os("XDG_DATA_HOME").filter(|path| !path.is_empty()).map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".local/share")));
+    match data_home {
+        Some(data_home) => data_home.join("chess-trainer"),
+        None => std::path::PathBuf::from(".chess-trainer")
+    }
+}
+
+fn main() {
+    use futures::StreamExt;
+
+    let (sender, mut lines) = futures::channel::mpsc::unbounded::<String>();
+    std::thread::spawn(move || {
+        use std::io::BufRead;
+        for line in std::io::stdin().lock().lines() {
+            match line {
+                Ok(line) => if sender.unbounded_send(line).is_err() { break; },
+                Err(_) => break
+            }
+        }
+    });
+
+    // `--seed N` repeats the choices of an earlier session, `--engine PATH` starts a UCI engine,
+    // `--games FILE` imports the games of a PGN for explore mode, `--data DIR` is where the progress is kept
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |name: &str| args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1)).cloned();
+    let seed = arg("--seed").and_then(|seed| seed.parse().ok());
+    let engine_path = arg("--engine");
+
+    let ui = UI::new(seed);
+    let storage = Rc::new(storage::FileStorage::new(arg("--data").map_or_else(data_directory, Into::into)));
+    if let Some(path) = arg("--games") {
+        match std::fs::read_to_string(&path) {
+            Ok(pgn) => {
+                // Added to the games of earlier imports, as with `UserAction::ImportGames`
+                let mut explorer = storage::load(&*storage, "explorer").unwrap_or_else(explorer::Explorer::new);
+                let import = explorer.add_pgn(&pgn);
+                let saved = storage::save(&*storage, "explorer", &explorer);
+                ui.state.borrow_mut().import = Some((import, saved));
+            },
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                return;
+            }
+        }
+    }
+
+    util::run_local(async move {
+        if let Some(path) = engine_path {
+            match engine::uci::UciEngine::start(std::process::Command::new(&path), UCI_DEPTH).await {
+                Ok(engine) => ui.state.borrow_mut().uci_engine = Some(Rc::new(engine)),
+                Err(error) => {
+                    eprintln!("{}: {}", path, error);
+                    return;
+                }
+            }
+        }
+
+        util::spawn_local(trainer::train(ui.clone(), storage));
+
+        while let Some(line) = lines.next().await {
+            if !ui.handle_input(line.trim()) {
+                break;
+            }
+        }
+    });
+
+    println!();
+}
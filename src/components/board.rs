@@ -1,11 +1,16 @@
 pub type LinkRef = std::rc::Rc<std::cell::RefCell<Option<yew::ComponentLink<Board>>>>;
 
+/// From a square to another. The third field is the width, 1 for a normal arrow
 #[derive(Clone)]
-pub struct Arrow(pub shakmaty::Square, pub shakmaty::Square);
+pub struct Arrow(pub shakmaty::Square, pub shakmaty::Square, pub f32);
+
+/// A short text shown in the corner of a square, e.g. the number of lines following a move
+#[derive(Clone)]
+pub struct Label(pub shakmaty::Square, pub String);
 
 impl From<&shakmaty::Move> for Arrow {
     fn from(m: &shakmaty::Move) -> Self {
-        Arrow(m.from().unwrap(), m.to())
+        Arrow(m.from().unwrap(), m.to(), 1.0)
     }
 }
 
@@ -14,6 +19,10 @@ pub struct BoardProps {
     pub link_ref: LinkRef,
     pub board: std::rc::Rc<shakmaty::Chess>,
     pub arrows: Vec<Arrow>,
+    #[prop_or_default]
+    pub labels: Vec<Label>,
+    #[prop_or_default]
+    pub highlights: Vec<shakmaty::Square>,
     pub on_user_move: yew::Callback<shakmaty::Move>,
     pub reverse: bool
 }
@@ -23,6 +32,8 @@ pub struct Board {
     board: std::rc::Rc<shakmaty::Chess>,
     selected: Option<shakmaty::Square>,
     arrows: Vec<Arrow>,
+    labels: Vec<Label>,
+    highlights: Vec<shakmaty::Square>,
     container_ref: yew::NodeRef,
     on_user_move: yew::Callback<shakmaty::Move>,
     reverse: bool
@@ -97,6 +108,8 @@ impl yew::Component for Board {
             board: props.board,
             selected: None,
             arrows: props.arrows,
+            labels: props.labels,
+            highlights: props.highlights,
             container_ref: yew::NodeRef::default(),
             on_user_move: props.on_user_move,
             reverse: props.reverse
@@ -116,6 +129,8 @@ impl yew::Component for Board {
     fn change(&mut self, props: Self::Properties) -> yew::ShouldRender {
         self.board = props.board;
         self.arrows = props.arrows;
+        self.labels = props.labels;
+        self.highlights = props.highlights;
         self.on_user_move = props.on_user_move;
         self.reverse = props.reverse;
 
@@ -152,7 +167,7 @@ impl yew::Component for Board {
 
         let reverse = self.reverse;
 
-        let make_arrow = |from: shakmaty::Square, to: shakmaty::Square| {
+        let make_arrow = |from: shakmaty::Square, to: shakmaty::Square, width: f32| {
             let mut x0 = from.file() as u8 as f32;
             let mut y0 = 7.0 - from.rank() as u8 as f32;
             let x1 = to.file() as u8 as f32;
@@ -172,7 +187,21 @@ impl yew::Component for Board {
             let transform = format!("translate({},{}) rotate({})", x0, y0, angle);
 
             yew::html_nested! {
-                <polygon transform=transform points=arrow_polygon(0.2,length) class="red" stroke="none" />   
+                <polygon transform=transform points=arrow_polygon(0.2 * width,length) class="red" stroke="none" />   
+            }
+        };
+
+        let make_label = |square: shakmaty::Square, text: &str| {
+            let mut x = square.file() as u8 as f32;
+            let mut y = 7.0 - square.rank() as u8 as f32;
+            if reverse {
+                x = 7.0 - x;
+                y = 7.0 - y;
+            }
+
+            // top right corner of the square
+            yew::html_nested! {
+                <text x=(x + 0.35).to_string() y=(y - 0.22).to_string() font-size="0.25" class="label">{text}</text>
             }
         };
 
@@ -193,6 +222,13 @@ impl yew::Component for Board {
                         }).collect::<yew::Html>()
                     }
                 }
+                {
+                    self.highlights.iter().map(|square| {
+                        yew::html! {
+                            <cb-square-marker class={get_class_for_position(*square, reverse) + " hint"} />
+                        }
+                    }).collect::<yew::Html>()
+                }
                 {
                     if let Some(selected) = self.selected {
                         yew::html! {
@@ -226,7 +262,10 @@ impl yew::Component for Board {
                 }
                 <svg viewBox="-0.5 -0.5 8 8">
                     {
-                        self.arrows.iter().map(|Arrow(from, to)| { make_arrow(*from, *to) }).collect::<yew::Html>()
+                        self.arrows.iter().map(|Arrow(from, to, width)| { make_arrow(*from, *to, *width) }).collect::<yew::Html>()
+                    }
+                    {
+                        self.labels.iter().map(|Label(square, text)| { make_label(*square, text) }).collect::<yew::Html>()
                     }
                 </svg>
             </cb-container>
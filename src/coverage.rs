@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+/// The order in which the lines are walked in coverage mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Order {
+    /// The order of the PGN
+    Tree,
+    ShortestFirst
+}
+
+/// The set of lines the student has mastered, i.e. played through without mistakes or hints
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Coverage {
+    mastered: HashSet<String>
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_mastered(&self, id: &str) -> bool {
+        self.mastered.contains(id)
+    }
+
+    pub fn master(&mut self, id: &str) {
+        self.mastered.insert(id.to_string());
+    }
+
+    /// Number of the given lines which are mastered
+    pub fn count<'a>(&self, ids: impl IntoIterator<Item=&'a str>) -> usize {
+        ids.into_iter().filter(|id| self.is_mastered(id)).count()
+    }
+
+    /// Picks the index of the first line in the given order which is not mastered yet, skipping `current`.
+    /// Once everything is mastered, the lines are repeated in the same order, starting after `current`
+    pub fn choose(&self, ids: &[&str], order: Order, current: Option<&str>) -> Option<usize> {
+        let mut indices: Vec<usize> = (0..ids.len()).collect();
+        if order == Order::ShortestFirst {
+            indices.sort_by_key(|&index| ids[index].split(' ').count());
+        }
+
+        let next = indices.iter().copied().find(|&index| Some(ids[index]) != current && !self.is_mastered(ids[index]));
+        if next.is_some() {
+            return next;
+        }
+
+        let position = indices.iter().position(|&index| Some(ids[index]) == current);
+        match position {
+            Some(position) => Some(indices[(position + 1) % indices.len()]),
+            None => indices.first().copied()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Coverage, Order};
+
+    #[test]
+    fn walks_all_lines() {
+        let ids = ["e4 e5 Nf3 Nf6 Nxe5", "e4 e5 Nc3 Nf6", "d4 d5 c4"];
+        let mut coverage = Coverage::new();
+
+        assert_eq!(coverage.choose(&ids, Order::Tree, None), Some(0));
+        assert_eq!(coverage.choose(&ids, Order::ShortestFirst, None), Some(2));
+        assert_eq!(coverage.choose(&ids, Order::Tree, Some(ids[0])), Some(1));
+
+        coverage.master(ids[0]);
+        coverage.master(ids[2]);
+        assert_eq!(coverage.count(ids.iter().copied()), 2);
+        assert_eq!(coverage.choose(&ids, Order::Tree, Some(ids[0])), Some(1));
+        assert_eq!(coverage.choose(&ids, Order::ShortestFirst, None), Some(1));
+
+        coverage.master(ids[1]);
+        assert_eq!(coverage.choose(&ids, Order::Tree, Some(ids[1])), Some(2));
+        assert_eq!(coverage.choose(&ids, Order::ShortestFirst, Some(ids[0])), Some(2));
+    }
+}
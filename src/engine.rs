@@ -0,0 +1,364 @@
+use crate::util::DynFuture;
+use shakmaty::{Chess, Color, Move, Position, Role, Setup, Square};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod uci;
+
+/// How good a position is for the side to move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Score {
+    /// In hundredths of a pawn
+    Centipawns(i32),
+    /// Mate in the number of moves, negative if the side to move gets mated.
+    /// 0 is a mate on the board, for the side which gave it
+    Mate(i32)
+}
+
+impl Score {
+    /// The same score for the other side
+    pub fn flip(self) -> Score {
+        match self {
+            Score::Centipawns(centipawns) => Score::Centipawns(-centipawns),
+            Score::Mate(moves) => Score::Mate(-moves)
+        }
+    }
+
+    /// Centipawns to compare scores by. Mates count as more than any material, the shorter the more
+    pub fn centipawns(self) -> i32 {
+        match self {
+            Score::Centipawns(centipawns) => centipawns,
+            Score::Mate(moves) if moves >= 0 => MATE - moves,
+            Score::Mate(moves) => -MATE - moves
+        }
+    }
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Score::Centipawns(centipawns) => write!(f, "{:+.2}", centipawns as f64 / 100.0),
+            Score::Mate(moves) => write!(f, "#{}", moves)
+        }
+    }
+}
+
+pub struct Analysis {
+    pub best_move: Move,
+    /// For the side to move
+    pub score: Score
+}
+
+/// Something which finds good moves. The answer may take a while, e.g. from another process
+pub trait Engine {
+    /// The best move and the score of the position, `None` if the game is over or the engine failed
+    fn analyse(&self, pos: &Chess) -> DynFuture<Option<Analysis>>;
+}
+
+/// Value of a checkmate. Mates in fewer plies score higher, so the shortest one is played
+const MATE: i32 = 100_000;
+
+fn material(role: Role) -> i32 {
+    match role {
+        Role::Pawn => 100,
+        Role::Knight => 320,
+        Role::Bishop => 330,
+        Role::Rook => 500,
+        Role::Queen => 900,
+        Role::King => 0
+    }
+}
+
+// Piece square tables from white's point of view, a8 first and h1 last (as they are printed)
+#[rustfmt::skip]
+const PAWN: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20
+];
+
+#[rustfmt::skip]
+const KING: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20
+];
+
+fn square_value(role: Role, color: Color, square: Square) -> i32 {
+    let table = match role {
+        Role::Pawn => &PAWN,
+        Role::Knight => &KNIGHT,
+        Role::Bishop => &BISHOP,
+        Role::Rook => &ROOK,
+        Role::Queen => &QUEEN,
+        Role::King => &KING
+    };
+
+    // The tables start with the 8th rank, which is the last one for white
+    let index = match color {
+        Color::White => usize::from(square) ^ 56,
+        Color::Black => usize::from(square)
+    };
+
+    table[index]
+}
+
+/// Material and piece placement, from the point of view of the side to move
+fn evaluate(pos: &Chess) -> i32 {
+    let score: i32 = pos.board().pieces().map(|(square, piece)| {
+        let value = material(piece.role) + square_value(piece.role, piece.color, square);
+        if piece.color == Color::White { value } else { -value }
+    }).sum();
+
+    if pos.turn() == Color::White { score } else { -score }
+}
+
+/// Captures of valuable pieces by cheap ones first, as they cut off the search early
+fn order_moves(moves: &mut [Move]) {
+    moves.sort_by_key(|m| match m.capture() {
+        Some(captured) => material(m.role()) - 10 * material(captured),
+        None => 0
+    });
+}
+
+/// A small alpha-beta search, strong enough to play on after the repertoire ends. It searches one ply
+/// deeper at a time, up to the depth or until it has looked at the given number of positions
+pub struct AlphaBeta {
+    /// In plies. Captures are followed beyond it until the position is quiet
+    depth: u32,
+    max_nodes: u64,
+    /// Positions looked at in the current search
+    nodes: std::cell::Cell<u64>
+}
+
+impl AlphaBeta {
+    pub fn new(depth: u32) -> Self {
+        AlphaBeta { depth: depth.max(1), max_nodes: u64::MAX, nodes: std::cell::Cell::new(0) }
+    }
+
+    /// Stops the search after about `nodes` positions, with the best move of the last depth searched
+    /// completely. In the browser, the search blocks the page while it runs
+    pub fn limit_nodes(mut self, nodes: u64) -> Self {
+        self.max_nodes = nodes;
+        self
+    }
+
+    /// The best move for the side to move, `None` if the game is over
+    pub fn best_move(&self, pos: &Chess) -> Option<Move> {
+        self.search_root(pos).map(|(m, _)| m)
+    }
+
+    fn out_of_nodes(&self) -> bool {
+        self.nodes.get() > self.max_nodes
+    }
+
+    fn search_root(&self, pos: &Chess) -> Option<(Move, i32)> {
+        if pos.is_insufficient_material() {
+            return None;
+        }
+
+        let mut moves: Vec<Move> = pos.legal_moves().into_iter().collect();
+        order_moves(&mut moves);
+        self.nodes.set(0);
+
+        let mut result: Option<(Move, i32)> = None;
+        for depth in 1..=self.depth {
+            // The best move of the last depth is searched first
+            if let Some((best, _)) = &result {
+                let index = moves.iter().position(|m| m == best).unwrap();
+                moves[..=index].rotate_right(1);
+            }
+
+            let mut alpha = -MATE - 1;
+            let mut best = None;
+            for m in &moves {
+                let mut child = pos.clone();
+                child.play_unchecked(m);
+                let score = -self.search(&child, depth - 1, 1, -MATE - 1, -alpha);
+                if self.out_of_nodes() {
+                    break;
+                }
+                if best.is_none() || score > alpha {
+                    alpha = score;
+                    best = Some(m.clone());
+                }
+            }
+
+            // An unfinished depth only counts if there is nothing better
+            if !self.out_of_nodes() || result.is_none() {
+                result = best.map(|m| (m, alpha)).or(result);
+            }
+            if self.out_of_nodes() {
+                break;
+            }
+        }
+
+        result
+    }
+
+    fn search(&self, pos: &Chess, depth: u32, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        if depth == 0 {
+            return self.quiesce(pos, alpha, beta);
+        }
+
+        self.nodes.set(self.nodes.get() + 1);
+        if self.out_of_nodes() {
+            return 0;
+        }
+
+        let mut moves: Vec<Move> = pos.legal_moves().into_iter().collect();
+        if moves.is_empty() {
+            return if pos.is_check() { -MATE + ply } else { 0 };
+        }
+        if pos.is_insufficient_material() {
+            return 0;
+        }
+
+        order_moves(&mut moves);
+        for m in moves {
+            let mut child = pos.clone();
+            child.play_unchecked(&m);
+            let score = -self.search(&child, depth - 1, ply + 1, -beta, -alpha);
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+
+    /// Follows the captures, so that the evaluation doesn't stop in the middle of an exchange
+    fn quiesce(&self, pos: &Chess, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes.set(self.nodes.get() + 1);
+        if self.out_of_nodes() {
+            return 0;
+        }
+
+        let standing = evaluate(pos);
+        if standing >= beta {
+            return beta;
+        }
+        alpha = alpha.max(standing);
+
+        let mut captures: Vec<Move> = pos.capture_moves().into_iter().collect();
+        order_moves(&mut captures);
+        for m in captures {
+            let mut child = pos.clone();
+            child.play_unchecked(&m);
+            let score = -self.quiesce(&child, -beta, -alpha);
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+}
+
+impl Engine for AlphaBeta {
+    fn analyse(&self, pos: &Chess) -> DynFuture<Option<Analysis>> {
+        let analysis = self.search_root(pos).map(|(best_move, score)| {
+            // A mate found `MATE - score` plies ahead
+            let score = if score.abs() > MATE - 1000 {
+                let moves = (MATE - score.abs() + 1) / 2;
+                Score::Mate(if score > 0 { moves } else { -moves })
+            } else {
+                Score::Centipawns(score)
+            };
+
+            Analysis { best_move, score }
+        });
+
+        Box::pin(std::future::ready(analysis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AlphaBeta;
+    use shakmaty::{fen::Fen, CastlingMode, Chess, Square};
+
+    fn position(fen: &str) -> Chess {
+        fen.parse::<Fen>().unwrap().position(CastlingMode::Standard).unwrap()
+    }
+
+    #[test]
+    fn finds_mate_and_material() {
+        // Back rank mate
+        let pos = position("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1");
+        let m = AlphaBeta::new(2).best_move(&pos).unwrap();
+        assert_eq!((m.from(), m.to()), (Some(Square::A1), Square::A8));
+
+        // The queen is taken, even though the knight is lost for it
+        let pos = position("4k3/8/8/3q4/8/4N3/8/4K3 w - - 0 1");
+        let m = AlphaBeta::new(3).best_move(&pos).unwrap();
+        assert_eq!(m.to(), Square::D5);
+
+        let pos = position("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(AlphaBeta::new(3).best_move(&pos), None);
+
+        // Out of nodes, the search still finds a move
+        let pos = position("4k3/8/8/3q4/8/4N3/8/4K3 w - - 0 1");
+        let m = AlphaBeta::new(10).limit_nodes(1000).best_move(&pos).unwrap();
+        assert_eq!(m.to(), Square::D5);
+    }
+}
@@ -0,0 +1,196 @@
+use super::{Analysis, Engine, Score};
+use crate::util::DynFuture;
+use std::io::Write;
+
+/// The pipes to the engine process. Requests are sent one at a time, as the answers
+/// can only be told apart by their order
+struct Connection {
+    child: std::process::Child,
+    stdin: std::process::ChildStdin,
+    /// The lines the engine printed, read on a separate thread
+    lines: futures::channel::mpsc::UnboundedReceiver<String>,
+    /// Searches whose `bestmove` wasn't read, because the request was dropped while waiting for it
+    pending: usize
+}
+
+impl Connection {
+    fn send(&mut self, command: &str) -> std::io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    /// The lines up to the first one starting with `prefix`, including it
+    async fn read_until(&mut self, prefix: &str) -> std::io::Result<Vec<String>> {
+        use futures::StreamExt;
+
+        let mut lines = Vec::new();
+        loop {
+            let line = self.lines.next().await.ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "the engine quit")
+            })?;
+
+            let done = line.starts_with(prefix);
+            lines.push(line);
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+
+    /// Stops the searches of dropped requests and reads their answers, so that the next answer
+    /// belongs to the next request
+    async fn drain(&mut self) -> std::io::Result<()> {
+        while self.pending > 0 {
+            self.send("stop")?;
+            self.read_until("bestmove").await?;
+            self.pending -= 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.wait();
+    }
+}
+
+/// An engine speaking UCI over stdin and stdout, e.g. Stockfish
+#[derive(Clone)]
+pub struct UciEngine {
+    connection: std::rc::Rc<futures::lock::Mutex<Connection>>,
+    depth: u32
+}
+
+impl UciEngine {
+    /// Starts the engine process and waits until it is ready. `depth` is the search depth of each analysis
+    pub async fn start(mut command: std::process::Command, depth: u32) -> std::io::Result<UciEngine> {
+        use std::io::BufRead;
+
+        let mut child = command
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (sender, lines) = futures::channel::mpsc::unbounded();
+        std::thread::spawn(move || {
+            for line in std::io::BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if sender.unbounded_send(line).is_err() { break; },
+                    Err(_) => break
+                }
+            }
+        });
+
+        let mut connection = Connection { child, stdin, lines, pending: 0 };
+        connection.send("uci")?;
+        connection.read_until("uciok").await?;
+        connection.send("isready")?;
+        connection.read_until("readyok").await?;
+
+        Ok(UciEngine {
+            connection: std::rc::Rc::new(futures::lock::Mutex::new(connection)),
+            depth
+        })
+    }
+}
+
+impl Engine for UciEngine {
+    fn analyse(&self, pos: &shakmaty::Chess) -> DynFuture<Option<Analysis>> {
+        let connection = self.connection.clone();
+        let depth = self.depth;
+        let pos = pos.clone();
+
+        Box::pin(async move {
+            let mut connection = connection.lock().await;
+            connection.drain().await.ok()?;
+            connection.send(&format!("position fen {}", shakmaty::fen::fen(&pos))).ok()?;
+            connection.send(&format!("go depth {}", depth)).ok()?;
+
+            // If this future is dropped before the answer is read, the next request reads it
+            connection.pending += 1;
+            let lines = connection.read_until("bestmove").await.ok()?;
+            connection.pending -= 1;
+
+            parse_analysis(&pos, &lines)
+        })
+    }
+}
+
+/// Reads the answer to `go`: the score of the last `info` line which has one, and the `bestmove`
+fn parse_analysis(pos: &shakmaty::Chess, lines: &[String]) -> Option<Analysis> {
+    let best_move = lines.last()?.split_whitespace().nth(1)?;
+    let best_move = best_move.parse::<shakmaty::uci::Uci>().ok()?.to_move(pos).ok()?;
+
+    let score = lines.iter().rev().find_map(|line| {
+        let mut words = line.split_whitespace().skip_while(|&word| word != "score").skip(1);
+        let kind = words.next()?;
+        let value = words.next()?.parse().ok()?;
+        match kind {
+            "cp" => Some(Score::Centipawns(value)),
+            "mate" => Some(Score::Mate(value)),
+            _ => None
+        }
+    })?;
+
+    Some(Analysis { best_move, score })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UciEngine;
+    use crate::engine::{Engine, Score};
+
+    /// Answers like an engine which likes e4, and e5 after it. It takes a while to think
+    const SCRIPT: &str = r#"
+        while read command; do
+            case "$command" in
+                uci) echo "id name Scripted"; echo "uciok";;
+                isready) echo "readyok";;
+                position*) position="$command";;
+                go*)
+                    sleep 0.1
+                    case "$position" in
+                        *" w KQkq "*) echo "info depth 1 score cp 12 pv e2e4"; echo "info depth 2 score cp 35 pv e2e4 e7e5"; echo "bestmove e2e4";;
+                        *) echo "info depth 2 score cp -30 pv e7e5"; echo "bestmove e7e5";;
+                    esac;;
+                quit) exit 0;;
+            esac
+        done
+    "#;
+
+    #[test]
+    fn talks_to_the_engine() {
+        crate::util::run_local(async {
+            let mut command = std::process::Command::new("sh");
+            command.arg("-c").arg(SCRIPT);
+            let engine = UciEngine::start(command, 2).await.unwrap();
+
+            let pos = shakmaty::Chess::default();
+            let analysis = engine.analyse(&pos).await.unwrap();
+            assert_eq!(analysis.best_move.to(), shakmaty::Square::E4);
+            assert_eq!(analysis.score, Score::Centipawns(35));
+
+            // Requests are answered in order
+            let (first, second) = futures::join!(engine.analyse(&pos), engine.analyse(&pos));
+            assert!(first.is_some() && second.is_some());
+
+            // The answer to a dropped request is not taken for the next one
+            assert!(crate::util::timeout(engine.analyse(&pos), 20).await.is_none());
+            let e4 = analysis.best_move;
+            let mut after_e4 = pos.clone();
+            shakmaty::Position::play_unchecked(&mut after_e4, &e4);
+            let analysis = engine.analyse(&after_e4).await.unwrap();
+            assert_eq!(analysis.best_move.to(), shakmaty::Square::E5);
+            assert_eq!(analysis.score, Score::Centipawns(-30));
+        });
+
+        let command = std::process::Command::new("true");
+        let result = crate::util::run_local(UciEngine::start(command, 2));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use crate::pgn::games::{read_games, Game};
+use crate::pgn::movetree::MoveTree;
+use shakmaty::{Chess, Color, Position, Setup};
+
+/// Only the opening of each game is imported, which keeps the store small
+const MAX_PLY: usize = 30;
+
+/// How a move did in the imported games
+#[derive(Clone, Copy, Default, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct MoveStatistics {
+    pub games: u32,
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32,
+    /// Sum of the ratings of the players who played the move, over the `rated` games
+    rating_sum: u64,
+    rated: u32
+}
+
+impl MoveStatistics {
+    /// The share of the points for the side which played the move, from 0 to 1
+    pub fn score(&self, color: Color) -> f64 {
+        let games = self.white_wins + self.draws + self.black_wins;
+        if games == 0 {
+            return 0.5;
+        }
+
+        let wins = if color == Color::White { self.white_wins } else { self.black_wins };
+        (wins as f64 + self.draws as f64 / 2.0) / games as f64
+    }
+
+    /// Of the players who played the move, if their rating is known
+    pub fn average_rating(&self) -> Option<u32> {
+        if self.rated == 0 { None } else { Some((self.rating_sum / self.rated as u64) as u32) }
+    }
+}
+
+/// How many games of a PGN were imported, see `Explorer::add_pgn`
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Import {
+    pub imported: usize,
+    /// Games which could not be read, see `pgn::games::Games::skipped`
+    pub skipped: usize
+}
+
+/// The moves played from each position of a games database, like the opening explorer of an online database
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Explorer {
+    /// By position (as EPD, so that the move counters don't matter) and by move in SAN
+    positions: HashMap<String, HashMap<String, MoveStatistics>>,
+    games: u32
+}
+
+/// A reply of the opponent which the repertoire doesn't answer, although it prepares others
+#[derive(Clone, PartialEq, Debug)]
+pub struct Gap {
+    /// The moves up to the position, see `Variation::id`. Empty for the starting position. If several
+    /// lines reach it, the shortest one
+    pub line: String,
+    /// In SAN
+    pub reply: String,
+    /// How often the reply was played
+    pub games: u32,
+    /// How often any move was played from the position
+    pub position_games: u32
+}
+
+impl Explorer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Number of imported games
+    pub fn games(&self) -> u32 {
+        self.games
+    }
+
+    /// Imports the main line of all games of the PGN
+    pub fn add_pgn(&mut self, pgn: &str) -> Import {
+        let games = read_games(pgn);
+        for game in &games.games {
+            self.add_game(game);
+        }
+
+        Import { imported: games.games.len(), skipped: games.skipped }
+    }
+
+    fn add_game(&mut self, game: &Game) {
+        let rating = |tag| game.tag(tag).and_then(|rating| rating.parse::<u32>().ok());
+        let white_rating = rating("WhiteElo");
+        let black_rating = rating("BlackElo");
+
+        self.games += 1;
+        let mut pos = Chess::default();
+        for (ply, m) in game.moves.iter().take(MAX_PLY).enumerate() {
+            let san = shakmaty::san::San::from_move(&pos, m).to_string();
+            let statistics = self.positions.entry(shakmaty::fen::epd(&pos)).or_default().entry(san).or_default();
+            pos.play_unchecked(m);
+
+            statistics.games += 1;
+            match game.result.as_str() {
+                "1-0" => statistics.white_wins += 1,
+                "0-1" => statistics.black_wins += 1,
+                "1/2-1/2" => statistics.draws += 1,
+                _ => {}
+            }
+
+            // White plays the even plies
+            let rating = if ply % 2 == 0 { white_rating } else { black_rating };
+            if let Some(rating) = rating {
+                statistics.rating_sum += rating as u64;
+                statistics.rated += 1;
+            }
+        }
+    }
+
+    /// How the move did from the position, `None` if it was never played there
+    pub fn get(&self, pos: &Chess, m: &shakmaty::Move) -> Option<&MoveStatistics> {
+        let san = shakmaty::san::San::from_move(pos, m).to_string();
+        self.positions.get(&shakmaty::fen::epd(pos))?.get(&san)
+    }
+
+    /// All moves played from the position in SAN, the most popular first
+    pub fn moves(&self, pos: &Chess) -> Vec<(String, MoveStatistics)> {
+        let mut moves: Vec<(String, MoveStatistics)> = match self.positions.get(&shakmaty::fen::epd(pos)) {
+            Some(moves) => moves.iter().map(|(san, statistics)| (san.clone(), *statistics)).collect(),
+            None => Vec::new()
+        };
+
+        moves.sort_by(|(a_san, a), (b_san, b)| b.games.cmp(&a.games).then_with(|| a_san.cmp(b_san)));
+        moves
+    }
+
+    /// The replies of the opponent played in the imported games which are missing from the tree, the most
+    /// frequent first. Only positions where the tree has at least one reply count, the ends of the lines don't.
+    /// A position reached by several lines has the replies of all of them
+    pub fn gaps(&self, tree: &MoveTree, student: Color) -> Vec<Gap> {
+        // The line up to each position and the replies of the tree, by position (as EPD, like the imported
+        // games), in the SAN of `San::from_move` like the imported moves
+        let mut positions: HashMap<String, (String, Chess, Vec<String>)> = HashMap::new();
+        for line in tree.lines() {
+            let (last, before) = line.split_last().unwrap();
+            let mut pos = Chess::default();
+            for san in before {
+                let m = san.parse::<shakmaty::san::San>().unwrap().to_move(&pos).unwrap();
+                pos.play_unchecked(&m);
+            }
+            if pos.turn() == student {
+                continue;
+            }
+
+            let m = last.parse::<shakmaty::san::San>().unwrap().to_move(&pos).unwrap();
+            let reply = shakmaty::san::San::from_move(&pos, &m).to_string();
+            let line = before.join(" ");
+            let position = positions.entry(shakmaty::fen::epd(&pos)).or_insert_with(|| (line.clone(), pos, Vec::new()));
+            if before.len() < position.0.split_whitespace().count() {
+                position.0 = line;
+            }
+            if !position.2.contains(&reply) {
+                position.2.push(reply);
+            }
+        }
+
+        let mut gaps = Vec::new();
+        for (_, (line, pos, replies)) in positions {
+            let moves = self.moves(&pos);
+            let position_games = moves.iter().map(|(_, statistics)| statistics.games).sum();
+            for (reply, statistics) in moves {
+                if !replies.contains(&reply) {
+                    gaps.push(Gap { line: line.clone(), reply, games: statistics.games, position_games });
+                }
+            }
+        }
+
+        gaps.sort_by(|a, b| b.games.cmp(&a.games).then_with(|| a.line.cmp(&b.line)).then_with(|| a.reply.cmp(&b.reply)));
+        gaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Explorer, Gap, Import};
+    use crate::pgn::movetree::MoveTree;
+    use shakmaty::{Chess, Color, Position};
+
+    const GAMES: &str = r#"
+[Event "Club"]
+[White "A"]
+[Black "B"]
+[WhiteElo "1800"]
+[BlackElo "1600"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {Main} (2. Nc3) Nc6 1-0
+
+[WhiteElo "2000"]
+[Result "1/2-1/2"]
+
+1. e4 c5 1/2-1/2
+
+1. d4 Nf6 2. Bxf7 0-1
+
+1. e4 e5 2. Nf3 Nf6 0-1
+"#;
+
+    #[test]
+    fn counts_moves_by_position() {
+        let mut explorer = Explorer::new();
+        assert_eq!(explorer.add_pgn(GAMES), Import { imported: 3, skipped: 1 });
+        assert_eq!(explorer.games(), 3);
+
+        let pos = Chess::default();
+        let moves = explorer.moves(&pos);
+        assert_eq!(moves.iter().map(|(san, _)| san.as_str()).collect::<Vec<_>>(), ["e4"]);
+
+        let e4 = &moves[0].1;
+        assert_eq!((e4.games, e4.white_wins, e4.draws, e4.black_wins), (3, 1, 1, 1));
+        assert_eq!(e4.average_rating(), Some(1900));
+        assert_eq!(e4.score(Color::White), 0.5);
+
+        let m = pos.legal_moves().into_iter().find(|m| m.to() == shakmaty::Square::E4).unwrap();
+        let mut after_e4 = pos.clone();
+        after_e4.play_unchecked(&m);
+        assert_eq!(explorer.get(&pos, &m), Some(e4));
+        assert_eq!(explorer.moves(&after_e4)[0].0, "e5");
+        assert_eq!(explorer.moves(&after_e4)[0].1.score(Color::Black), 0.5);
+        assert_eq!(explorer.moves(&after_e4)[0].1.average_rating(), Some(1600));
+    }
+
+    #[test]
+    fn finds_unanswered_replies() {
+        let mut explorer = Explorer::new();
+        explorer.add_pgn(GAMES);
+
+        // c5 is missing after e4. After Nf3, the line ends, so Nc6 and Nf6 are no gaps
+        let mut tree = MoveTree::new();
+        tree.add_pgn("1. e4 e5 2. Nf3");
+        assert_eq!(explorer.gaps(&tree, Color::White), [
+            Gap { line: "e4".to_string(), reply: "c5".to_string(), games: 1, position_games: 3 }
+        ]);
+
+        // As black, white's first move is the reply
+        let mut tree = MoveTree::new();
+        tree.add_pgn("1. d4 d5");
+        assert_eq!(explorer.gaps(&tree, Color::Black)[0].reply, "e4");
+
+        // Each line answers a move of the other one by transposition
+        let mut explorer = Explorer::new();
+        explorer.add_pgn("1. d4 Nf6 2. c4 e6 3. Nf3 *\n\n1. c4 e6 2. d4 Nf6 3. Nc3 *\n\n1. d4 Nf6 2. c4 e6 3. g3 *");
+        let mut tree = MoveTree::new();
+        tree.add_pgn("1. d4 (1. c4 e6 2. d4 Nf6 3. Nf3 b6) 1... Nf6 2. c4 e6 3. Nc3 Bb4");
+        assert_eq!(explorer.gaps(&tree, Color::Black), [
+            Gap { line: "d4 Nf6 c4 e6".to_string(), reply: "g3".to_string(), games: 1, position_games: 3 }
+        ]);
+    }
+}
@@ -0,0 +1,88 @@
+/// Student moves per level: level 1 has the lines with up to 3 moves to remember, level 2 up to 6, ...
+const MOVES_PER_LEVEL: usize = 3;
+
+/// Groups the lines into levels of increasing depth. Only the moves of the student count,
+/// as the trainer's moves don't need to be remembered
+pub struct Levels {
+    levels: Vec<Vec<usize>>
+}
+
+impl Levels {
+    /// `ids` are the line ids, see `Variation::id`
+    pub fn new(ids: &[&str], student: shakmaty::Color) -> Self {
+        let student_moves = |id: &str| {
+            let plies = id.split(' ').count();
+            match student {
+                shakmaty::Color::White => plies.div_ceil(2),
+                shakmaty::Color::Black => plies / 2
+            }
+        };
+
+        let mut levels: Vec<Vec<usize>> = Vec::new();
+        for (index, id) in ids.iter().enumerate() {
+            let level = student_moves(id).saturating_sub(1) / MOVES_PER_LEVEL;
+            if levels.len() <= level {
+                levels.resize(level + 1, Vec::new());
+            }
+            levels[level].push(index);
+        }
+
+        // Depths without any line don't make a level
+        levels.retain(|lines| !lines.is_empty());
+        Levels { levels }
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Indices of the lines of the level
+    pub fn lines(&self, level: usize) -> &[usize] {
+        &self.levels[level]
+    }
+
+    /// The first level which still has lines which are not mastered. Later levels are locked.
+    /// If everything is mastered, this is the last level
+    pub fn current(&self, is_mastered: impl Fn(usize) -> bool) -> usize {
+        self.levels.iter()
+            .position(|lines| !lines.iter().all(|&index| is_mastered(index)))
+            .unwrap_or(self.levels.len().saturating_sub(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Levels;
+
+    #[test]
+    fn levels_by_depth() {
+        let ids = [
+            "e4 e5 Nf3 Nf6 Nxe5 Nc6 Nxc6 dxc6 d3 Bc5",
+            "e4 e5 Nf3 Nf6 Nc3",
+            "e4 e5 Nf3 Nf6 Nxe5 Nc6 Nxc6 dxc6 d3 Bc5 Bg5 Nxe4 Bxd8 Bxf2+ Ke2 Bg4#"
+        ];
+
+        let levels = Levels::new(&ids, shakmaty::Color::Black);
+        assert_eq!(levels.len(), 3);
+        assert_eq!(levels.lines(0), [1]);
+        assert_eq!(levels.lines(1), [0]);
+        assert_eq!(levels.lines(2), [2]);
+
+        assert_eq!(levels.current(|_| false), 0);
+        assert_eq!(levels.current(|index| index == 1), 1);
+        assert_eq!(levels.current(|index| index != 0), 1);
+        assert_eq!(levels.current(|_| true), 2);
+
+        // As white there is one more move to remember
+        let ids = ["e4 e5 Nf3 Nf6 Nxe5 Nc6 Nxc6", "e4 e5 Nf3 Nf6 Nc3"];
+        assert_eq!(Levels::new(&ids, shakmaty::Color::Black).lines(0), [0, 1]);
+
+        let levels = Levels::new(&ids, shakmaty::Color::White);
+        assert_eq!(levels.lines(0), [1]);
+        assert_eq!(levels.lines(1), [0]);
+    }
+}
@@ -0,0 +1,16 @@
+pub mod audit;
+pub mod components;
+pub mod coverage;
+pub mod engine;
+pub mod explorer;
+pub mod levels;
+pub mod mistakes;
+pub mod own_games;
+pub mod pgn;
+pub mod schedule;
+pub mod selector;
+pub mod session;
+pub mod statistics;
+pub mod storage;
+pub mod trainer;
+pub mod util;
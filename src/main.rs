@@ -1,14 +1,31 @@
 use yew::prelude::*;
-mod components;
-mod pgn;
-mod trainer;
-mod util;
+use chess_trainer::{components, engine, explorer, own_games, session, storage, trainer, util};
 
 enum GameMessage {
-    Init(shakmaty::Chess, bool),
+    Init(shakmaty::Chess, bool, shakmaty::Color),
     PlayMove(shakmaty::Move, Vec<components::board::Arrow>),
     UpdateArrows(Vec<components::board::Arrow>),
-    SetLearning(bool)
+    UpdateLabels(Vec<components::board::Label>),
+    UpdateHighlights(Vec<shakmaty::Square>),
+    SetHintPolicy(trainer::HintPolicy),
+    SetTimeLimit(u32),
+    SetEngineDepth(u32),
+    SetStartPoint(trainer::StartPoint),
+    ShowCountdown(Option<u32>),
+    ShowSeed(u64),
+    SetExplore(bool),
+    SetExam(bool),
+    SetLearning(bool),
+    ShowProgress(trainer::Mode, usize, usize),
+    ShowLevel(Option<trainer::Level>),
+    ShowFeedback(Option<trainer::Feedback>),
+    ShowExamResult(Vec<trainer::Deviation>),
+    ShowSession(session::Session),
+    ShowOffBook(Option<String>),
+    ShowExplorer(Option<trainer::ExplorerMoves>),
+    ShowImport(explorer::Import, bool),
+    SetPlayer(String),
+    ShowCheckedGames(Vec<own_games::CheckedGame>)
 }
 
 struct Game {
@@ -18,11 +35,36 @@ struct Game {
     board_link_ref: components::board::LinkRef,
     board: std::rc::Rc<shakmaty::Chess>,
     arrows: Vec<components::board::Arrow>,
+    labels: Vec<components::board::Label>,
+    highlights: Vec<shakmaty::Square>,
     user_move_channel: util::EventChannel<shakmaty::Move>,
     user_action_channel: util::EventChannel<trainer::UserAction>,
     learning_input_ref: yew::NodeRef,
+    storage: std::rc::Rc<dyn storage::Storage>,
     learning: bool,
-    explore: bool
+    hint_policy: trainer::HintPolicy,
+    time_limit: u32,
+    engine_depth: u32,
+    countdown: Option<u32>,
+    start_point: trainer::StartPoint,
+    seed: u64,
+    explore: bool,
+    exam: bool,
+    orientation: shakmaty::Color,
+    mode: trainer::Mode,
+    mastered: usize,
+    total: usize,
+    level: Option<trainer::Level>,
+    feedback: Option<trainer::Feedback>,
+    exam_result: Option<Vec<trainer::Deviation>>,
+    session: session::Session,
+    off_book: Option<String>,
+    explorer: Option<trainer::ExplorerMoves>,
+    /// The last import of games, and whether it was saved
+    import: Option<(explorer::Import, bool)>,
+    /// The student's name in their own games, empty to check all games
+    player: String,
+    checked_games: Vec<own_games::CheckedGame>
 }
 
 impl Component for Game {
@@ -30,25 +72,57 @@ impl Component for Game {
     type Properties = ();
 
     fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let storage = std::rc::Rc::new(storage::LocalStorage::new());
+        let learning = storage::load(&*storage, "learning").unwrap_or(true);
+
         Self {
             link: link,
             board_link_ref: Default::default(),
             board: std::rc::Rc::new(shakmaty::Chess::default()),
             arrows: Vec::new(),
+            labels: Vec::new(),
+            highlights: Vec::new(),
             user_move_channel: util::EventChannel::new(),
             user_action_channel: util::EventChannel::new(),
             learning_input_ref: Default::default(),
-            learning: true,
-            explore: false
+            storage,
+            learning,
+            hint_policy: Default::default(),
+            time_limit: 0,
+            engine_depth: 0,
+            countdown: None,
+            start_point: Default::default(),
+            seed: 0,
+            explore: false,
+            exam: false,
+            orientation: shakmaty::Color::White,
+            mode: trainer::Mode::default(),
+            mastered: 0,
+            total: 0,
+            level: None,
+            feedback: None,
+            exam_result: None,
+            session: session::Session::new(),
+            off_book: None,
+            explorer: None,
+            import: None,
+            player: String::new(),
+            checked_games: Vec::new()
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            GameMessage::Init(board, explore) => {
+            GameMessage::Init(board, explore, orientation) => {
                 self.board = board.into();
                 self.arrows = Vec::new();
+                self.labels = Vec::new();
+                self.highlights = Vec::new();
                 self.explore = explore;
+                self.orientation = orientation;
+                self.feedback = None;
+                self.exam_result = None;
+                self.countdown = None;
                 true
             },
             GameMessage::PlayMove(m, arrows) => {
@@ -57,6 +131,8 @@ impl Component for Game {
                 board.play_unchecked(&m);
                 self.board = board.into();
                 self.arrows = arrows;
+                self.labels = Vec::new();
+                self.highlights = Vec::new();
                 
                 true
             },
@@ -64,8 +140,91 @@ impl Component for Game {
                 self.arrows = arrows;
                 true
             },
+            GameMessage::UpdateLabels(labels) => {
+                self.labels = labels;
+                true
+            },
+            GameMessage::UpdateHighlights(highlights) => {
+                self.highlights = highlights;
+                true
+            },
+            GameMessage::SetHintPolicy(hint_policy) => {
+                self.hint_policy = hint_policy;
+                true
+            },
+            GameMessage::SetTimeLimit(time_limit) => {
+                self.time_limit = time_limit;
+                true
+            },
+            GameMessage::SetEngineDepth(engine_depth) => {
+                self.engine_depth = engine_depth;
+                true
+            },
+            GameMessage::SetStartPoint(start_point) => {
+                self.start_point = start_point;
+                true
+            },
+            GameMessage::ShowCountdown(countdown) => {
+                self.countdown = countdown;
+                true
+            },
+            GameMessage::ShowSeed(seed) => {
+                self.seed = seed;
+                true
+            },
+            GameMessage::SetExplore(explore) => {
+                self.explore = explore;
+                true
+            },
+            GameMessage::SetExam(exam) => {
+                self.exam = exam;
+                true
+            },
             GameMessage::SetLearning(learning) => {
                 self.learning = learning;
+                storage::save(&*self.storage, "learning", &learning);
+                true
+            },
+            GameMessage::ShowProgress(mode, mastered, total) => {
+                self.mode = mode;
+                self.mastered = mastered;
+                self.total = total;
+                true
+            },
+            GameMessage::ShowLevel(level) => {
+                self.level = level;
+                true
+            },
+            GameMessage::ShowFeedback(feedback) => {
+                self.feedback = feedback;
+                true
+            },
+            GameMessage::ShowExamResult(deviations) => {
+                self.exam_result = Some(deviations);
+                true
+            },
+            GameMessage::ShowSession(session) => {
+                self.session = session;
+                true
+            },
+            GameMessage::ShowOffBook(off_book) => {
+                self.off_book = off_book;
+                true
+            },
+            GameMessage::ShowExplorer(explorer) => {
+                self.explorer = explorer;
+                true
+            },
+            GameMessage::ShowImport(import, saved) => {
+                self.import = Some((import, saved));
+                true
+            },
+            GameMessage::SetPlayer(player) => {
+                self.player = player;
+                true
+            },
+            GameMessage::ShowCheckedGames(checked_games) => {
+                self.checked_games = checked_games;
                 true
             }
         }
@@ -80,10 +239,10 @@ impl Component for Game {
 
     fn rendered(&mut self, first_render: bool) {
         if first_render {
-            wasm_bindgen_futures::spawn_local(trainer::train(UI {
+            util::spawn_local(trainer::train(UI {
                 link: self.link.clone(),
                 board_link_ref: self.board_link_ref.clone()
-            }))
+            }, self.storage.clone()))
         }
     }
 
@@ -97,6 +256,67 @@ impl Component for Game {
             }
         });
 
+        let on_mode_change = self.user_action_channel.callback().reform(|data: ChangeData| {
+            let value = match data {
+                ChangeData::Select(select) => select.value(),
+                _ => String::new()
+            };
+            trainer::UserAction::SetMode(mode_from_value(&value))
+        });
+
+        let policy = self.hint_policy;
+        let threshold = |data: ChangeData| match data {
+            ChangeData::Value(value) => value.parse().unwrap_or(0),
+            _ => 0
+        };
+        let on_piece_change = self.link.callback(move |data| {
+            GameMessage::SetHintPolicy(trainer::HintPolicy { piece: threshold(data), ..policy })
+        });
+        let on_target_change = self.link.callback(move |data| {
+            GameMessage::SetHintPolicy(trainer::HintPolicy { target: threshold(data), ..policy })
+        });
+        let on_arrow_change = self.link.callback(move |data| {
+            GameMessage::SetHintPolicy(trainer::HintPolicy { arrow: threshold(data), ..policy })
+        });
+        let on_time_limit_change = self.link.callback(move |data| GameMessage::SetTimeLimit(threshold(data)));
+        let on_engine_depth_change = self.link.callback(move |data| GameMessage::SetEngineDepth(threshold(data)));
+
+        let start_ply = match self.start_point {
+            trainer::StartPoint::Ply(ply) => ply,
+            _ => 0
+        };
+        let on_start_point_change = self.link.callback(move |data| {
+            let value = match data {
+                ChangeData::Select(select) => select.value(),
+                _ => String::new()
+            };
+            GameMessage::SetStartPoint(match value.as_str() {
+                "ply" => trainer::StartPoint::Ply(start_ply),
+                "random" => trainer::StartPoint::Random,
+                _ => trainer::StartPoint::Beginning
+            })
+        });
+        let on_start_ply_change = self.link.callback(move |data| {
+            GameMessage::SetStartPoint(trainer::StartPoint::Ply(threshold(data) as usize))
+        });
+        let on_seed_change = self.user_action_channel.callback().reform(move |data| {
+            trainer::UserAction::Reseed(threshold(data).into())
+        });
+
+        let on_games_change = self.read_file(trainer::UserAction::ImportGames);
+        let player = Some(self.player.clone()).filter(|player| !player.is_empty());
+        let on_own_games_change = self.read_file(move |pgn| trainer::UserAction::CheckOwnGames(pgn, player.clone()));
+        let on_player_change = self.link.callback(|data| match data {
+            ChangeData::Value(value) => GameMessage::SetPlayer(value.trim().to_string()),
+            _ => GameMessage::SetPlayer(String::new())
+        });
+
+        let progress = match self.mode {
+            trainer::Mode::Coverage(_) => format!("{} / {} Varianten", self.mastered, self.total),
+            trainer::Mode::Review => format!("{} Fehler offen", self.total),
+            _ => String::new()
+        };
+
         let explore_icon = if self.explore {
             "images/icons/explore_off_black_24dp.svg"
         } else {
@@ -111,19 +331,185 @@ impl Component for Game {
                         <div />
                     </label>
                     {if self.learning {"Lernmodus (Pfeile anzeigen)"} else {"Übungsmodus (ohne Pfeile)"}}
+                    <select onchange=on_mode_change>
+                        {
+                            MODES.iter().map(|(mode, value, text)| html! {
+                                <option value=*value selected=self.mode == *mode>{text}</option>
+                            }).collect::<Html>()
+                        }
+                    </select>
+                    <span class="progress">{progress}</span>
+                </div>
+                <div class="game-settings">
+                    {"Hinweise nach Fehlern: Figur"}
+                    <input type="number" min="0" value=policy.piece.to_string() onchange=on_piece_change />
+                    {"Feld"}
+                    <input type="number" min="0" value=policy.target.to_string() onchange=on_target_change />
+                    {"Pfeil"}
+                    <input type="number" min="0" value=policy.arrow.to_string() onchange=on_arrow_change />
+                    {"Start"}
+                    <select onchange=on_start_point_change>
+                        <option value="beginning" selected=self.start_point == trainer::StartPoint::Beginning>{"Anfang"}</option>
+                        <option value="ply" selected=matches!(self.start_point, trainer::StartPoint::Ply(_))>{"ab Halbzug"}</option>
+                        <option value="random" selected=self.start_point == trainer::StartPoint::Random>{"zufällig"}</option>
+                    </select>
+                    {
+                        if let trainer::StartPoint::Ply(ply) = self.start_point {
+                            html! { <input type="number" min="0" value=ply.to_string() onchange=on_start_ply_change /> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    {"Zeit pro Zug (s)"}
+                    <input type="number" min="0" value=self.time_limit.to_string() onchange=on_time_limit_change />
+                    {"Weiterspielen (Engine-Tiefe)"}
+                    <input type="number" min="0" max=MAX_ENGINE_DEPTH.to_string() value=self.engine_depth.to_string() onchange=on_engine_depth_change />
+                    {"Seed"}
+                    <input type="number" min="0" value=self.seed.to_string() onchange=on_seed_change />
+                    {"Partien importieren (PGN)"}
+                    <input type="file" accept=".pgn" onchange=on_games_change />
+                    {"Eigene Partien prüfen: Name"}
+                    <input type="text" value=self.player.clone() onchange=on_player_change />
+                    <input type="file" accept=".pgn" onchange=on_own_games_change />
+                    <label>
+                        <input type="checkbox" checked=self.exam
+                            onclick=self.user_action_channel.callback_constant(trainer::UserAction::ToggleExam) />
+                        {"Prüfung"}
+                    </label>
                 </div>
+                {
+                    if let Some(level) = &self.level {
+                        html! {
+                            <div class="game-level">
+                                <div>{format!("Level {} / {}", level.number, level.count)}</div>
+                                <ul>
+                                    {
+                                        level.lines.iter().map(|(moves, mastered)| html! {
+                                            <li class=if *mastered { "mastered" } else { "" }>{moves}</li>
+                                        }).collect::<Html>()
+                                    }
+                                </ul>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    match &self.feedback {
+                        Some(trainer::Feedback::NotPreferred(main_move)) => html! {
+                            <div class="game-feedback">{format!("Gut, aber der Hauptzug ist {}", main_move)}</div>
+                        },
+                        Some(trainer::Feedback::EngineMove(best_move, score)) => html! {
+                            <div class="game-feedback">{format!("Die Engine spielt {} ({})", best_move, score)}</div>
+                        },
+                        Some(trainer::Feedback::Evaluation(score)) => html! {
+                            <div class="game-feedback">{format!("Bewertung: {}", score)}</div>
+                        },
+                        None => html! {}
+                    }
+                }
+                {
+                    match &self.off_book {
+                        Some(line) => html! {
+                            <div class="game-off-book">
+                                {format!("Nicht im Repertoire: {}", line)}
+                                <button onclick=self.user_action_channel.callback_constant(trainer::UserAction::AddToRepertoire)>
+                                    {"Hinzufügen"}
+                                </button>
+                            </div>
+                        },
+                        None => html! {}
+                    }
+                }
+                {
+                    match self.import {
+                        Some((import, saved)) => html! {
+                            <div class="game-feedback">
+                                {format!("{} Partien importiert, {} nicht lesbar", import.imported, import.skipped)}
+                                {if saved { "" } else { " (zu groß für den Speicher des Browsers, nur bis zum Schließen der Seite verfügbar)" }}
+                            </div>
+                        },
+                        None => html! {}
+                    }
+                }
+                { self.view_explorer() }
+                {
+                    if self.checked_games.is_empty() {
+                        html! {}
+                    } else {
+                        html! {
+                            <div class="game-summary">
+                                <div>{format!("{} eigene Partien geprüft", self.checked_games.len())}</div>
+                                <ul>
+                                    { self.checked_games.iter().map(|game| html! { <li>{game.describe()}</li> }).collect::<Html>() }
+                                </ul>
+                            </div>
+                        }
+                    }
+                }
+                {
+                    match self.countdown {
+                        Some(seconds) => html! {
+                            <div class=if seconds == 0 { "game-countdown timeout" } else { "game-countdown" }>
+                                {format!("⏱ {} s", seconds)}
+                            </div>
+                        },
+                        None => html! {}
+                    }
+                }
+                {
+                    match &self.exam_result {
+                        Some(deviations) if deviations.is_empty() => html! {
+                            <div class="game-summary">{"Prüfung bestanden, keine Fehler"}</div>
+                        },
+                        Some(deviations) => html! {
+                            <div class="game-summary">
+                                <div>{format!("Prüfung beendet, {} Fehler", deviations.len())}</div>
+                                { deviations.iter().map(|deviation| self.view_deviation(deviation)).collect::<Html>() }
+                            </div>
+                        },
+                        None => html! {}
+                    }
+                }
                 <components::board::Board
                     board=self.board.clone()
                     arrows=self.arrows.clone()
+                    labels=self.labels.clone()
+                    highlights=self.highlights.clone()
                     on_user_move=self.user_move_channel.callback()
                     link_ref=self.board_link_ref.clone()
-                    reverse=true />
+                    reverse=self.orientation == shakmaty::Color::Black />
                 <div class="desktop-flex-break" />
                 <div class="game-footer">
                     <components::iconbutton::IconButton
                         disabled=false
                         image=explore_icon
                         onclick=self.user_action_channel.callback_constant(trainer::UserAction::ToggleExplore) />
+                    {
+                        if self.explore {
+                            html! {
+                                <components::iconbutton::IconButton
+                                    disabled=false
+                                    image="images/icons/play_arrow_black_24dp.svg"
+                                    onclick=self.user_action_channel.callback_constant(trainer::UserAction::TrainFromHere) />
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    <components::iconbutton::IconButton
+                        disabled=false
+                        image="images/icons/swap_vert_black_24dp.svg"
+                        onclick=self.user_action_channel.callback_constant(trainer::UserAction::SwitchSide) />
+                    <components::iconbutton::IconButton
+                        disabled=false
+                        image="images/icons/navigate_before_black_24dp.svg"
+                        onclick=self.user_action_channel.callback_constant(trainer::UserAction::StepBack) />
+                    <components::iconbutton::IconButton
+                        disabled=false
+                        image="images/icons/navigate_next_black_24dp.svg"
+                        onclick=self.user_action_channel.callback_constant(trainer::UserAction::StepForward) />
                     <components::iconbutton::IconButton
                         disabled=false
                         image="images/icons/refresh_black_24dp.svg"
@@ -133,11 +519,150 @@ impl Component for Game {
                         image="images/icons/double_arrow_black_24dp.svg"
                         onclick=self.user_action_channel.callback_constant(trainer::UserAction::NextLevel) />
                 </div>
+                { self.view_session() }
             </div>
         }
     }
 }
 
+impl Game {
+    /// Reads the chosen PGN file in the background, then sends the action made from its text
+    fn read_file(&self, action: impl Fn(String) -> trainer::UserAction + 'static) -> Callback<ChangeData> {
+        let action = std::rc::Rc::new(action);
+        let send = self.user_action_channel.callback();
+        Callback::from(move |data: ChangeData| {
+            let file = match data {
+                ChangeData::Files(files) => files.get(0),
+                _ => None
+            };
+            if let Some(file) = file {
+                let action = action.clone();
+                let send = send.clone();
+                util::spawn_local(async move {
+                    if let Ok(text) = wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                        if let Some(pgn) = text.as_string() {
+                            send.emit(action(pgn));
+                        }
+                    }
+                });
+            }
+        })
+    }
+
+    /// How the moves of the position did in the imported games
+    fn view_explorer(&self) -> Html {
+        let explorer = match &self.explorer {
+            Some(explorer) => explorer,
+            None => return html! {}
+        };
+
+        html! {
+            <div class="game-explorer"><table>
+                <tr>
+                    <th>{"Zug"}</th>
+                    <th>{"Partien"}</th>
+                    <th>{"Punkte"}</th>
+                    <th>{"Ø Elo"}</th>
+                </tr>
+                {
+                    explorer.moves.iter().map(|(san, statistics)| html! {
+                        <tr>
+                            <td>{san}</td>
+                            <td>{statistics.games}</td>
+                            <td>{if statistics.games > 0 { format!("{:.0} %", 100.0 * statistics.score(explorer.turn)) } else { String::new() }}</td>
+                            <td>{statistics.average_rating().map_or(String::new(), |rating| rating.to_string())}</td>
+                        </tr>
+                    }).collect::<Html>()
+                }
+                <tr>
+                    <td colspan="4">{format!("{} Partien importiert", explorer.games)}</td>
+                </tr>
+            </table></div>
+        }
+    }
+
+    /// Totals of the session, with a table of the lines when expanded
+    fn view_session(&self) -> Html {
+        if self.session.lines().is_empty() {
+            return html! {};
+        }
+
+        let seconds = |record: &session::LineRecord| format!("{:.1} s", record.time_per_move() / 1000.0);
+        let total = self.session.total();
+
+        html! {
+            <div class="game-session">
+                <details>
+                    <summary>
+                        {format!("Sitzung: {} Varianten, {} beendet, {} von {} Zügen auf Anhieb richtig, {} Hinweise, {} pro Zug",
+                            self.session.lines().len(), self.session.completed(), total.first_try, total.moves, total.hints, seconds(&total))}
+                    </summary>
+                    <table>
+                        <tr>
+                            <th>{"Variante"}</th>
+                            <th>{"Züge"}</th>
+                            <th>{"Auf Anhieb"}</th>
+                            <th>{"Hinweise"}</th>
+                            <th>{"Zeit pro Zug"}</th>
+                            <th>{"Beendet"}</th>
+                        </tr>
+                        {
+                            self.session.lines().iter().map(|record| html! {
+                                <tr>
+                                    <td>{trainer::format_moves(&record.line)}</td>
+                                    <td>{record.moves}</td>
+                                    <td>{record.first_try}</td>
+                                    <td>{record.hints}</td>
+                                    <td>{seconds(record)}</td>
+                                    <td>{if record.completed { "✓" } else { "" }}</td>
+                                </tr>
+                            }).collect::<Html>()
+                        }
+                    </table>
+                </details>
+            </div>
+        }
+    }
+
+    /// A small board with the position of a wrong move in exam mode, the expected move as arrow
+    fn view_deviation(&self, deviation: &trainer::Deviation) -> Html {
+        let san = |m| shakmaty::san::San::from_move(&deviation.position, m).to_string();
+
+        html! {
+            <div class="deviation">
+                <components::board::Board
+                    board=std::rc::Rc::new(deviation.position.clone())
+                    arrows=vec![(&deviation.expected).into()]
+                    on_user_move=Callback::noop()
+                    link_ref=components::board::LinkRef::default()
+                    reverse=self.orientation == shakmaty::Color::Black />
+                <div>{format!("Erwartet {}, gespielt {}", san(&deviation.expected), san(&deviation.played))}</div>
+            </div>
+        }
+    }
+}
+
+/// The built-in engine blocks the page while it searches
+const MAX_ENGINE_DEPTH: u32 = 4;
+const ENGINE_NODES: u64 = 50_000;
+
+const MODES: [(trainer::Mode, &str, &str); 10] = [
+    (trainer::Mode::Repetition, "repetition", "Wiederholen"),
+    (trainer::Mode::Sequential, "sequential", "Der Reihe nach"),
+    (trainer::Mode::Shuffled, "shuffled", "Zufällige Variante"),
+    (trainer::Mode::WeakestFirst, "weakest", "Schwächste zuerst"),
+    (trainer::Mode::Weighted, "weighted", "Nach Häufigkeit"),
+    (trainer::Mode::Coverage(chess_trainer::coverage::Order::Tree), "tree", "Alle Varianten"),
+    (trainer::Mode::Coverage(chess_trainer::coverage::Order::ShortestFirst), "shortest", "Alle Varianten (kurze zuerst)"),
+    (trainer::Mode::Levels, "levels", "Level"),
+    (trainer::Mode::Review, "review", "Fehler wiederholen"),
+    (trainer::Mode::Random, "random", "Zufällige Antworten")
+];
+
+fn mode_from_value(value: &str) -> trainer::Mode {
+    MODES.iter().find(|(_, v, _)| *v == value).map_or(trainer::Mode::default(), |(mode, _, _)| *mode)
+}
+
 #[derive(Clone)]
 struct UI {
     link: ComponentLink<Game>,
@@ -145,8 +670,16 @@ struct UI {
 }
 
 impl trainer::UI for UI {
-    fn init(&self, pos: &'_ shakmaty::Chess, explore: bool) {
-        self.link.send_message(GameMessage::Init(pos.clone(), explore));
+    fn init(&self, pos: &'_ shakmaty::Chess, explore: bool, orientation: shakmaty::Color) {
+        self.link.send_message(GameMessage::Init(pos.clone(), explore, orientation));
+    }
+
+    fn set_explore(&self, explore: bool) {
+        self.link.send_message(GameMessage::SetExplore(explore));
+    }
+
+    fn set_exam(&self, exam: bool) {
+        self.link.send_message(GameMessage::SetExam(exam));
     }
 
     fn play_move(&self, m: shakmaty::Move, arrows: Vec<components::board::Arrow>) {
@@ -157,6 +690,14 @@ impl trainer::UI for UI {
         self.link.send_message(GameMessage::UpdateArrows(arrows))
     }
 
+    fn update_labels(&self, labels: Vec<components::board::Label>) {
+        self.link.send_message(GameMessage::UpdateLabels(labels))
+    }
+
+    fn update_highlights(&self, squares: Vec<shakmaty::Square>) {
+        self.link.send_message(GameMessage::UpdateHighlights(squares))
+    }
+
     fn shake(&self) {
         if let Some(ref board_link) = *self.board_link_ref.borrow() {
             if let Some(comp) = board_link.get_component() {
@@ -183,6 +724,87 @@ impl trainer::UI for UI {
         }
     }
 
+    fn show_progress(&self, mode: trainer::Mode, mastered: usize, total: usize) {
+        self.link.send_message(GameMessage::ShowProgress(mode, mastered, total));
+    }
+
+    fn show_level(&self, level: Option<trainer::Level>) {
+        self.link.send_message(GameMessage::ShowLevel(level));
+    }
+
+    fn show_feedback(&self, feedback: Option<trainer::Feedback>) {
+        self.link.send_message(GameMessage::ShowFeedback(feedback));
+    }
+
+    fn show_exam_result(&self, deviations: Vec<trainer::Deviation>) {
+        self.link.send_message(GameMessage::ShowExamResult(deviations));
+    }
+
+    fn show_session(&self, session: session::Session) {
+        self.link.send_message(GameMessage::ShowSession(session));
+    }
+
+    fn show_off_book(&self, line: Option<String>) {
+        self.link.send_message(GameMessage::ShowOffBook(line));
+    }
+
+    fn show_explorer(&self, moves: Option<trainer::ExplorerMoves>) {
+        self.link.send_message(GameMessage::ShowExplorer(moves));
+    }
+
+    fn show_import(&self, import: explorer::Import, saved: bool) {
+        self.link.send_message(GameMessage::ShowImport(import, saved));
+    }
+
+    fn show_checked_games(&self, games: Vec<own_games::CheckedGame>) {
+        self.link.send_message(GameMessage::ShowCheckedGames(games));
+    }
+
+    fn show_countdown(&self, seconds: Option<u32>) {
+        self.link.send_message(GameMessage::ShowCountdown(seconds));
+    }
+
+    fn start_point(&self) -> trainer::StartPoint {
+        match self.link.get_component() {
+            Some(game) => game.start_point,
+            None => Default::default()
+        }
+    }
+
+    /// Given as `?seed=N` in the URL
+    fn seed(&self) -> Option<u64> {
+        let search = web_sys::window()?.location().search().ok()?;
+        search.trim_start_matches('?').split('&')
+            .find_map(|parameter| parameter.strip_prefix("seed="))
+            .and_then(|seed| seed.parse().ok())
+    }
+
+    fn show_seed(&self, seed: u64) {
+        self.link.send_message(GameMessage::ShowSeed(seed));
+    }
+
+    fn time_limit(&self) -> u32 {
+        match self.link.get_component() {
+            Some(game) => game.time_limit,
+            None => 0
+        }
+    }
+
+    fn engine(&self) -> Option<std::rc::Rc<dyn engine::Engine>> {
+        let depth = self.link.get_component()?.engine_depth;
+        if depth == 0 {
+            return None;
+        }
+
+        // The search runs on the page's thread, so it is cut short before the page hangs
+        Some(std::rc::Rc::new(engine::AlphaBeta::new(depth.min(MAX_ENGINE_DEPTH)).limit_nodes(ENGINE_NODES)))
+    }
+
+    fn explore_engine(&self) -> Option<std::rc::Rc<dyn engine::Engine>> {
+        // Searching after every move would make exploring sluggish
+        None
+    }
+
     fn show_hints(&self) -> bool {
         if let Some(game) = self.link.get_component() {
             return game.learning;
@@ -190,6 +812,13 @@ impl trainer::UI for UI {
 
         true
     }
+
+    fn hint_policy(&self) -> trainer::HintPolicy {
+        match self.link.get_component() {
+            Some(game) => game.hint_policy,
+            None => Default::default()
+        }
+    }
 }
 
 fn main() {
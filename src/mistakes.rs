@@ -0,0 +1,94 @@
+/// A position where the student played a wrong move
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Mistake {
+    pub fen: String,
+    /// The move of the line, in SAN
+    pub expected: String,
+    /// The wrong move in SAN, empty if the time ran out
+    pub played: String,
+    /// The line it happened in, see `Variation::id`
+    pub line: String
+}
+
+impl Mistake {
+    fn is_same_position(&self, other: &Mistake) -> bool {
+        self.fen == other.fen && self.line == other.line
+    }
+}
+
+/// The positions which still have to be reviewed, the oldest first
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Mistakes {
+    mistakes: Vec<Mistake>
+}
+
+impl Mistakes {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.mistakes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mistakes.is_empty()
+    }
+
+    /// Adds a mistake to the end of the queue. A position which is already queued is moved
+    /// to the end, with the latest wrong move
+    pub fn record(&mut self, mistake: Mistake) {
+        self.mistakes.retain(|queued| !queued.is_same_position(&mistake));
+        self.mistakes.push(mistake);
+    }
+
+    /// Removes the position of the mistake, once the student found the right move
+    pub fn resolve(&mut self, mistake: &Mistake) {
+        self.mistakes.retain(|queued| !queued.is_same_position(mistake));
+    }
+
+    /// Drops the mistakes which don't fit anymore, e.g. of lines which are not in the repertoire
+    pub fn retain(&mut self, keep: impl Fn(&Mistake) -> bool) {
+        self.mistakes.retain(|mistake| keep(mistake));
+    }
+
+    /// The next position to review. `current` is only chosen if it is the only one
+    pub fn choose(&self, current: Option<&Mistake>) -> Option<&Mistake> {
+        self.mistakes.iter()
+            .find(|mistake| current.is_none_or(|current| !mistake.is_same_position(current)))
+            .or_else(|| self.mistakes.first())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mistake, Mistakes};
+
+    fn mistake(fen: &str, played: &str) -> Mistake {
+        Mistake {
+            fen: fen.to_string(),
+            expected: "Nf6".to_string(),
+            played: played.to_string(),
+            line: "e4 e5 Nf3 Nf6".to_string()
+        }
+    }
+
+    #[test]
+    fn queue_of_positions() {
+        let mut mistakes = Mistakes::new();
+        assert_eq!(mistakes.choose(None), None);
+
+        mistakes.record(mistake("a", "Nc6"));
+        mistakes.record(mistake("b", "d6"));
+        mistakes.record(mistake("a", "d6"));
+        assert_eq!(mistakes.len(), 2);
+        assert_eq!(mistakes.choose(None), Some(&mistake("b", "d6")));
+        assert_eq!(mistakes.choose(Some(&mistake("b", "d6"))), Some(&mistake("a", "d6")));
+
+        mistakes.resolve(&mistake("b", "Nc6"));
+        assert_eq!(mistakes.choose(Some(&mistake("a", "d6"))), Some(&mistake("a", "d6")));
+
+        mistakes.retain(|mistake| mistake.line != "e4 e5 Nf3 Nf6");
+        assert_eq!(mistakes.len(), 0);
+    }
+}
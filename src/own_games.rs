@@ -0,0 +1,157 @@
+use crate::mistakes::Mistake;
+use crate::pgn::games::Game;
+use crate::pgn::movetree::Variations;
+use shakmaty::Setup;
+
+/// Where a game of the student left the repertoire
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Departure {
+    /// The student played a move which is not in the repertoire. It is reviewed like a mistake in training
+    Deviated(Mistake),
+    /// The opponent played a move the repertoire doesn't answer, in SAN
+    OpponentLeft(String),
+    /// The game followed a line to its end
+    LineEnded,
+    /// The game was over before it left the repertoire
+    InBook
+}
+
+/// A game of the student, compared with the repertoire
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CheckedGame {
+    /// The players, e.g. `Carlsen - Nepomniachtchi`
+    pub players: String,
+    /// The moves the game followed the repertoire for, see `Variation::id`
+    pub line: String,
+    pub departure: Departure
+}
+
+impl CheckedGame {
+    /// A line of the report, e.g. `Me - A, 1. e4 e5 2. Nf3 Nc6: abgewichen mit Bc4 statt Bb5`
+    pub fn describe(&self) -> String {
+        let line = if self.line.is_empty() { "Grundstellung".to_string() } else { crate::trainer::format_moves(&self.line) };
+        let departure = match &self.departure {
+            Departure::Deviated(mistake) => format!("abgewichen mit {} statt {}", mistake.played, mistake.expected),
+            Departure::OpponentLeft(reply) => format!("Gegner verlässt das Repertoire mit {}", reply),
+            Departure::LineEnded => "Variante zu Ende".to_string(),
+            Departure::InBook => "Partie im Repertoire beendet".to_string()
+        };
+
+        format!("{}, {}: {}", self.players, line, departure)
+    }
+}
+
+/// Follows each game through the repertoire up to the first move which isn't in it. The student plays
+/// `student` in all games, or only in the ones where `player` is the name of that side, if given
+pub fn check_games(variations: &Variations<'_>, student: shakmaty::Color, player: Option<&str>, games: &[Game]) -> Vec<CheckedGame> {
+    let side_tag = if student == shakmaty::Color::White { "White" } else { "Black" };
+    games.iter()
+        .filter(|game| player.is_none_or(|player| game.tag(side_tag) == Some(player)))
+        .map(|game| check_game(variations, student, game))
+        .collect()
+}
+
+fn check_game(variations: &Variations<'_>, student: shakmaty::Color, game: &Game) -> CheckedGame {
+    let players = format!("{} - {}", game.tag("White").unwrap_or("?"), game.tag("Black").unwrap_or("?"));
+    let mut iter = variations.get(0).iter();
+    let mut line = Vec::new();
+
+    for m in &game.moves {
+        let pos = iter.position().clone();
+        let expected = match iter.preferred().or_else(|| iter.peek()) {
+            Some(expected) => expected,
+            None => return CheckedGame { players, line: line.join(" "), departure: Departure::LineEnded }
+        };
+
+        if !iter.try_switch(m) {
+            let san = |m| shakmaty::san::San::from_move(&pos, m).to_string();
+            let departure = if pos.turn() == student {
+                // The line of the expected move, so that the mistake is reviewed in it
+                iter.try_switch(&expected);
+                Departure::Deviated(Mistake {
+                    fen: shakmaty::fen::fen(&pos),
+                    expected: san(&expected),
+                    played: san(m),
+                    line: iter.line_id()
+                })
+            } else {
+                Departure::OpponentLeft(san(m))
+            };
+
+            return CheckedGame { players, line: line.join(" "), departure };
+        }
+
+        line.push(shakmaty::san::San::from_move(&pos, m).to_string());
+        iter.next();
+    }
+
+    CheckedGame { players, line: line.join(" "), departure: Departure::InBook }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_games, Departure};
+    use crate::pgn::games::read_games;
+    use crate::pgn::movetree::MoveTree;
+
+    const GAMES: &str = r#"
+[White "Me"]
+[Black "A"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bc4 1-0
+
+[White "Me"]
+[Black "B"]
+
+1. e4 c5 2. Nf3 0-1
+
+[White "Me"]
+[Black "C"]
+
+1. e4 e5 2. Nf3 Nf6 3. d4 Nxe4 1/2-1/2
+
+[White "D"]
+[Black "Me"]
+
+1. d4 d5 0-1
+
+[White "Me"]
+[Black "E"]
+
+1. e4 e5 *
+
+[White "Me"]
+[Black "F"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 1-0
+"#;
+
+    #[test]
+    fn finds_where_games_leave_the_repertoire() {
+        let mut tree = MoveTree::new();
+        tree.add_pgn("1. e4 e5 2. Nf3 Nc6 (2... Nf6 3. Nxe5) 3. Bb5");
+        let variations = std::rc::Rc::new(tree).get_all_variations();
+
+        let checked = check_games(&variations, shakmaty::Color::White, Some("Me"), &read_games(GAMES).games);
+        assert_eq!(checked.len(), 5);
+
+        match &checked[0].departure {
+            Departure::Deviated(mistake) => {
+                assert_eq!((mistake.expected.as_str(), mistake.played.as_str()), ("Bb5", "Bc4"));
+                assert_eq!(mistake.line, "e4 e5 Nf3 Nc6 Bb5");
+            },
+            departure => panic!("{:?}", departure)
+        }
+        assert_eq!(checked[0].line, "e4 e5 Nf3 Nc6");
+        assert_eq!(checked[1].departure, Departure::OpponentLeft("c5".to_string()));
+
+        // The student deviated in the other line, which has another expected move
+        match &checked[2].departure {
+            Departure::Deviated(mistake) => assert_eq!((mistake.expected.as_str(), mistake.line.as_str()), ("Nxe5", "e4 e5 Nf3 Nf6 Nxe5")),
+            departure => panic!("{:?}", departure)
+        }
+        assert_eq!(checked[3].departure, Departure::InBook);
+        assert_eq!(checked[4].departure, Departure::LineEnded);
+        assert_eq!(checked[4].line, "e4 e5 Nf3 Nc6 Bb5");
+    }
+}
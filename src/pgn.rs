@@ -1,3 +1,4 @@
+pub mod games;
 pub mod lexer;
 pub mod movetree;
 mod tree;
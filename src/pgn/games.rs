@@ -0,0 +1,147 @@
+use super::lexer::{Token, TokenIterator};
+use shakmaty::{Chess, Position};
+
+/// A game of a PGN with several games, e.g. a database export. Only the main line is kept
+#[derive(Clone, Default, Debug)]
+pub struct Game {
+    /// The tags in the order of the PGN, e.g. `("White", "Carlsen")`
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<shakmaty::Move>,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: String
+}
+
+impl Game {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// The games of a PGN
+#[derive(Default, Debug)]
+pub struct Games {
+    pub games: Vec<Game>,
+    /// Games we could not follow, e.g. with an illegal move or text we cannot read
+    pub skipped: usize
+}
+
+/// A tag value without the backslashes which escape quotes and backslashes
+fn unescape(value: &[u8]) -> String {
+    let mut result = Vec::with_capacity(value.len());
+    let mut escaped = false;
+    for &ch in value {
+        if ch == b'\\' && !escaped {
+            escaped = true;
+        } else {
+            result.push(ch);
+            escaped = false;
+        }
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// Reads the games of the PGN. A game we cannot follow is skipped, the next one is read as usual
+pub fn read_games(pgn: &str) -> Games {
+    let mut games = Games::default();
+    let mut game = Game::default();
+    let mut pos = Chess::default();
+    let mut broken = false;
+    let mut depth = 0;
+
+    let mut finish = |game: Game, broken: bool| {
+        if broken {
+            games.skipped += 1;
+        } else if !game.moves.is_empty() {
+            games.games.push(game);
+        }
+    };
+
+    for token in TokenIterator::new(pgn.as_bytes()) {
+        match token {
+            Token::Tag(name, value) => {
+                // Tags after the moves start the next game, even if the result was missing
+                if broken || !game.moves.is_empty() {
+                    game.result = "*".to_string();
+                    finish(std::mem::take(&mut game), broken);
+                    pos = Chess::default();
+                    broken = false;
+                    depth = 0;
+                }
+                game.tags.push((String::from_utf8_lossy(name).into_owned(), unescape(value)));
+            },
+            Token::StartVariation => depth += 1,
+            Token::EndVariation => depth -= 1,
+            Token::SanMove(san) if depth == 0 && !broken => {
+                let m = std::str::from_utf8(san).ok()
+                    .and_then(|san| san.parse::<shakmaty::san::SanPlus>().ok())
+                    .and_then(|san| san.san.to_move(&pos).ok());
+                match m {
+                    Some(m) => {
+                        pos.play_unchecked(&m);
+                        game.moves.push(m);
+                    },
+                    None => broken = true
+                }
+            },
+            Token::Unknown(_) => broken = true,
+            Token::Result(result) => {
+                game.result = String::from_utf8_lossy(result).into_owned();
+                finish(std::mem::take(&mut game), broken);
+                pos = Chess::default();
+                broken = false;
+                depth = 0;
+            },
+            _ => {}
+        }
+    }
+
+    // The last game may lack its result
+    if broken || !game.moves.is_empty() {
+        game.result = "*".to_string();
+        finish(game, broken);
+    }
+
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_games;
+
+    #[test]
+    fn skips_only_broken_games() {
+        let pgn = r#"
+% An escape line
+[Event "The \"big\" one"]
+[White "A"]
+
+1. e4 e5 2. Nf3 Nc6 ; the main line
+3. Bb5 1-0
+
+[White "B"]
+
+1. e4 d5 2. exd5 c6 3. dxc6 Nf6 4. cxb7 Nc6 5. bxa8=Q 1-0
+
+[White "C"]
+
+1. e4 e5 2. Ke3 Nc6 0-1
+
+[White "D"]
+
+1. d4 d5 2. c4 &% 1/2-1/2
+
+[White "E"]
+
+1. c4 e5
+"#;
+
+        let games = read_games(pgn);
+        assert_eq!(games.games.iter().map(|game| game.tag("White").unwrap()).collect::<Vec<_>>(), ["A", "B", "E"]);
+        assert_eq!(games.skipped, 2);
+        assert_eq!(games.games[0].tag("Event"), Some("The \"big\" one"));
+        assert_eq!(games.games[0].moves.len(), 5);
+        assert_eq!(games.games[1].moves.len(), 9);
+        assert_eq!(games.games[2].result, "*");
+    }
+}
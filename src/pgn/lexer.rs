@@ -3,7 +3,16 @@ pub enum Token<'source> {
     StartVariation,
     EndVariation,
     SanMove(&'source [u8]),
-    MoveNr
+    MoveNr,
+    /// Numeric annotation glyph, `$1` or its short form `!`
+    Nag(u8),
+    /// The text between `{` and `}`
+    Comment(&'source [u8]),
+    Tag(&'source [u8], &'source [u8]),
+    /// The end of a game: `1-0`, `0-1`, `1/2-1/2` or `*`
+    Result(&'source [u8]),
+    /// Text we cannot read, up to the next whitespace. The game it is in can't be trusted
+    Unknown(&'source [u8])
 }
 
 pub struct TokenIterator<'source>(&'source [u8]);
@@ -18,16 +27,34 @@ impl<'source> Iterator for TokenIterator<'source> {
     type Item = Token<'source>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let input = self.0;
-        let (input, _) = nom::bytes::complete::take_while::<_, _, nom::error::Error<_>>(nom::character::is_space)(input).unwrap();
+        let mut input = self.0;
+        loop {
+            let (rest, _) = nom::bytes::complete::take_while::<_, _, nom::error::Error<_>>(|ch: u8| ch.is_ascii_whitespace())(input).unwrap();
+            // Escape lines (starting with `%`) are for other programs
+            match rest.first() {
+                Some(b'%') => input = rest_of_line(rest).0,
+                _ => {
+                    input = rest;
+                    break;
+                }
+            }
+        }
         if input.is_empty() {
             self.0 = input;
-            None
-        } else {
-            let (input, token) = token(input).unwrap();
-            self.0 = input;
+            return None;
+        }
 
-            Some(token)
+        // Input we cannot read is skipped up to the next whitespace, the reader decides what to do with the game
+        match token(input) {
+            Ok((input, token)) => {
+                self.0 = input;
+                Some(token)
+            },
+            Err(_) => {
+                let end = input.iter().position(|ch| ch.is_ascii_whitespace()).unwrap_or(input.len()).max(1);
+                self.0 = &input[end..];
+                Some(Token::Unknown(&input[..end]))
+            }
         }
     }
 }
@@ -43,6 +70,8 @@ fn san(input: &[u8]) -> nom::IResult<&[u8], &[u8]> {
             b'A'..=b'Z' => true,
             b'0'..=b'9' => true,
             b'-' => true,
+            // Promotions, e.g. `e8=Q`
+            b'=' => true,
             _ => false
         }
     })(input)
@@ -59,6 +88,51 @@ fn san_plus(input: &[u8]) -> nom::IResult<&[u8], Token> {
     Ok((input, Token::SanMove(result)))
 }
 
+fn nag(input: &[u8]) -> nom::IResult<&[u8], Token> {
+    let (input, _) = nom::bytes::complete::tag(b"$")(input)?;
+    let (input, value) = number(input)?;
+    let value = std::str::from_utf8(value).unwrap().parse().unwrap_or(0);
+
+    Ok((input, Token::Nag(value)))
+}
+
+fn suffix_annotation(input: &[u8]) -> nom::IResult<&[u8], Token> {
+    let (input, result) = nom::bytes::complete::take_while1(|ch| ch == b'!' || ch == b'?')(input)?;
+    let value = match result {
+        b"!" => 1,
+        b"?" => 2,
+        b"!!" => 3,
+        b"??" => 4,
+        b"!?" => 5,
+        b"?!" => 6,
+        _ => 0
+    };
+
+    Ok((input, Token::Nag(value)))
+}
+
+fn comment(input: &[u8]) -> nom::IResult<&[u8], Token> {
+    let (input, _) = nom::bytes::complete::tag(b"{")(input)?;
+    let (input, text) = nom::bytes::complete::take_while(|ch| ch != b'}')(input)?;
+    let (input, _) = nom::bytes::complete::tag(b"}")(input)?;
+
+    Ok((input, Token::Comment(text)))
+}
+
+/// The rest of the line, without the line break
+fn rest_of_line(input: &[u8]) -> (&[u8], &[u8]) {
+    let end = input.iter().position(|&ch| ch == b'\n').unwrap_or(input.len());
+    (&input[end..], &input[..end])
+}
+
+/// A comment from `;` to the end of the line
+fn line_comment(input: &[u8]) -> nom::IResult<&[u8], Token> {
+    let (input, _) = nom::bytes::complete::tag(b";")(input)?;
+    let (input, text) = rest_of_line(input);
+
+    Ok((input, Token::Comment(text)))
+}
+
 fn move_number(input: &[u8]) -> nom::IResult<&[u8], Token> {
     let (input, _) = number(input)?;
     let (input, _) = nom::bytes::complete::take_while1(|ch| ch == b'.')(input)?;
@@ -66,6 +140,12 @@ fn move_number(input: &[u8]) -> nom::IResult<&[u8], Token> {
     Ok((input, Token::MoveNr))
 }
 
+fn game_result(input: &[u8]) -> nom::IResult<&[u8], Token> {
+    use nom::bytes::complete::tag;
+    let (input, result) = nom::branch::alt((tag(b"1-0"), tag(b"0-1"), tag(b"1/2-1/2"), tag(b"*")))(input)?;
+    Ok((input, Token::Result(result)))
+}
+
 fn start_variation(input: &[u8]) -> nom::IResult<&[u8], Token> {
     let (input, _) = nom::bytes::complete::tag(b"(")(input)?;
     Ok((input, Token::StartVariation))
@@ -76,6 +156,23 @@ fn end_variation(input: &[u8]) -> nom::IResult<&[u8], Token> {
     Ok((input, Token::EndVariation))
 }
 
+fn tag(input: &[u8]) -> nom::IResult<&[u8], Token> {
+    let (input, _) = nom::bytes::complete::tag(b"[")(input)?;
+    let (input, name) = nom::bytes::complete::take_while1(|ch| nom::character::is_alphanumeric(ch) || ch == b'_')(input)?;
+    let (input, _) = nom::bytes::complete::take_while(nom::character::is_space)(input)?;
+    let (input, _) = nom::bytes::complete::tag(b"\"")(input)?;
+    // Quotes and backslashes in the value are escaped with a backslash. They are left in the value
+    let (input, value) = nom::combinator::recognize(nom::multi::many0_count(nom::branch::alt((
+        nom::bytes::complete::take_while1(|ch| ch != b'"' && ch != b'\\'),
+        nom::combinator::recognize(nom::sequence::pair(nom::bytes::complete::tag(b"\\"), nom::bytes::complete::take(1usize)))
+    ))))(input)?;
+    let (input, _) = nom::bytes::complete::tag(b"\"")(input)?;
+    let (input, _) = nom::bytes::complete::take_while(nom::character::is_space)(input)?;
+    let (input, _) = nom::bytes::complete::tag(b"]")(input)?;
+
+    Ok((input, Token::Tag(name, value)))
+}
+
 fn token(input: &[u8]) -> nom::IResult<&[u8], Token> {
-    nom::branch::alt((start_variation, end_variation, move_number, san_plus))(input)
+    nom::branch::alt((tag, start_variation, end_variation, nag, suffix_annotation, comment, line_comment, move_number, game_result, san_plus))(input)
 }
@@ -2,10 +2,90 @@ use super::tree;
 use super::lexer::{Token, TokenIterator};
 use std::rc::Rc;
 
-type Node<'source> = tree::Node<&'source str>;
-type Tree<'source> = tree::Tree<&'source str>;
+/// The SAN of a move, borrowed from the PGN. Moves of a PGN which doesn't live as long as the tree
+/// are shared instead, see `MoveTree::add_owned_pgn`
+#[derive(Clone)]
+enum SanText<'source> {
+    Borrowed(&'source str),
+    Shared(Rc<str>)
+}
+
+impl<'source> std::ops::Deref for SanText<'source> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            SanText::Borrowed(san) => san,
+            SanText::Shared(san) => san
+        }
+    }
+}
+
+/// A move of the tree. Moves are the same if their SAN is, regardless of the annotations
+#[derive(Clone)]
+struct MoveData<'source> {
+    san: SanText<'source>,
+    /// Marked as the best move (`!` or `!!`), which is preferred over the other moves from the same position
+    preferred: bool,
+    /// How often the move should be played compared to the other moves from the same position,
+    /// e.g. the number of games it was played in. Given by a `[%weight 12]` command in a comment
+    weight: Option<u32>
+}
+
+impl<'source> MoveData<'source> {
+    fn new(san: SanText<'source>) -> Self {
+        MoveData { san, preferred: false, weight: None }
+    }
+}
+
+/// Reads the `[%weight 12]` command of a comment
+fn parse_weight(comment: &str) -> Option<u32> {
+    let start = comment.find("[%weight")? + "[%weight".len();
+    let end = start + comment[start..].find(']')?;
+    comment[start..end].trim().parse().ok()
+}
+
+/// The comment without its `[%weight 12]` command, which is written separately
+fn strip_weight(comment: &str) -> String {
+    let stripped = match comment.find("[%weight") {
+        Some(start) => match comment[start..].find(']') {
+            Some(end) => format!("{} {}", &comment[..start], &comment[start + end + 1..]),
+            None => comment.to_string()
+        },
+        None => comment.to_string()
+    };
+
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// NAGs (other than `!`) and comments of a move, kept to write them back with `MoveTree::to_pgn`
+#[derive(Default)]
+struct Remarks {
+    nags: Vec<u8>,
+    comments: Vec<String>
+}
+
+/// What the PGNs say besides the moves and the orientation
+#[derive(Default)]
+struct Extras {
+    /// In the order of the PGNs. A tag given by several PGNs is kept once
+    tags: Vec<(String, String)>,
+    /// By the id of the line up to the move (see `Variation::id`). The comments before the first move
+    /// have the empty id
+    remarks: std::collections::HashMap<String, Remarks>
+}
+
+impl<'source> PartialEq for MoveData<'source> {
+    fn eq(&self, other: &Self) -> bool {
+        *self.san == *other.san
+    }
+}
 
-pub struct MoveTree<'source>(Tree<'source>);
+type Node<'source> = tree::Node<MoveData<'source>>;
+type Tree<'source> = tree::Tree<MoveData<'source>>;
+
+/// The tree of all moves, together with the side the repertoire is meant for (`Orientation` tag)
+pub struct MoveTree<'source>(Tree<'source>, Option<shakmaty::Color>, Extras);
 
 pub struct Variation<'source> {
     tree: Rc<MoveTree<'source>>,
@@ -20,28 +100,37 @@ pub struct Variations<'source> {
 
 impl<'source> MoveTree<'source> {
     pub fn new() -> Self {
-        MoveTree(Tree::new())
+        MoveTree(Tree::new(), None, Extras::default())
     }
 
     pub fn add_pgn(&mut self, pgn: &'source str) {
         let mut iter = TokenIterator::new(pgn.as_bytes());
 
         let root = self.0.root.clone();
-        self.parse_internal(root, &mut iter);
+        self.parse_internal(root, &mut iter, &SanText::Borrowed);
+    }
+
+    /// Like `add_pgn`, for a PGN which doesn't live as long as the tree, e.g. a line the student added.
+    /// Its moves are copied
+    pub fn add_owned_pgn(&mut self, pgn: &str) {
+        let mut iter = TokenIterator::new(pgn.as_bytes());
+
+        let root = self.0.root.clone();
+        self.parse_internal(root, &mut iter, &|san: &str| SanText::Shared(san.into()));
     }
 
-    fn parse_internal(&mut self, node: Rc<Node<'source>>, iter: &mut TokenIterator<'source>) {
+    fn parse_internal<'pgn>(&mut self, node: Rc<Node<'source>>, iter: &mut TokenIterator<'pgn>, san: &impl Fn(&'pgn str) -> SanText<'source>) {
         let mut main = node;
         let mut start_variation = false;
         while let Some(token) = iter.next() {
             match token {
                 Token::SanMove(m) => {
-                    let m = std::str::from_utf8(m).unwrap();
+                    let m = MoveData::new(san(std::str::from_utf8(m).unwrap()));
 
                     if start_variation {
                         start_variation = false;
                         let fork = main.fork_or_find(&mut self.0, m);
-                        self.parse_internal(fork, iter);
+                        self.parse_internal(fork, iter, san);
                     } else {
                         main = main.branch_or_find(&mut self.0, m);
                     }
@@ -51,11 +140,64 @@ impl<'source> MoveTree<'source> {
                     start_variation = true;
                 },
                 Token::EndVariation => break,
+                Token::Comment(text) => {
+                    let text = std::str::from_utf8(text).unwrap();
+                    let weight = parse_weight(text);
+                    if let (Some(weight), Some(mut value)) = (weight, main.value(&self.0)) {
+                        value.weight = Some(weight);
+                        main.set_value(&mut self.0, value);
+                    }
+
+                    let comment = strip_weight(text);
+                    let remarks = self.remarks_mut(&main);
+                    if !comment.is_empty() && !remarks.comments.contains(&comment) {
+                        remarks.comments.push(comment);
+                    }
+                },
+                Token::Nag(nag) => {
+                    // Annotations follow the move they belong to, which is the last one of this variation
+                    if let Some(mut value) = main.value(&self.0) {
+                        if nag == 1 || nag == 3 {
+                            value.preferred = true;
+                            main.set_value(&mut self.0, value);
+                        }
+
+                        let remarks = self.remarks_mut(&main);
+                        if nag != 1 && !remarks.nags.contains(&nag) {
+                            remarks.nags.push(nag);
+                        }
+                    }
+                },
+                Token::Tag(b"Orientation", value) => {
+                    self.1 = match value {
+                        b"white" | b"White" => Some(shakmaty::Color::White),
+                        b"black" | b"Black" => Some(shakmaty::Color::Black),
+                        _ => self.1
+                    };
+                },
+                Token::Tag(name, value) => {
+                    let name = String::from_utf8_lossy(name).into_owned();
+                    if !self.2.tags.iter().any(|(tag, _)| *tag == name) {
+                        self.2.tags.push((name, String::from_utf8_lossy(value).into_owned()));
+                    }
+                },
                 _ => {}
             }
         }
     }
 
+    fn remarks_mut(&mut self, node: &Rc<Node<'source>>) -> &mut Remarks {
+        let mut nodes = Vec::new();
+        self.resolve_variation_internal(node.clone(), &mut nodes);
+        let id = self.line_id(&nodes);
+        self.2.remarks.entry(id).or_default()
+    }
+
+    /// The side the student plays, if the PGN specifies it
+    pub fn orientation(&self) -> Option<shakmaty::Color> {
+        self.1
+    }
+
     pub fn get_all_variations(self: Rc<Self>) -> Variations<'source> {
         let mut variations = Vec::new();
         self.get_all_variations_from_node(&self.0.root, &mut variations);
@@ -78,6 +220,128 @@ impl<'source> MoveTree<'source> {
         }
     }
 
+    /// The moves up to each node of the tree, parents before their children
+    pub fn lines(&self) -> Vec<Vec<String>> {
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        self.lines_from_node(&self.0.root, &mut line, &mut lines);
+
+        lines
+    }
+
+    fn lines_from_node(&self, node: &Rc<Node<'source>>, line: &mut Vec<String>, result: &mut Vec<Vec<String>>) {
+        for child in node.get_children(&self.0) {
+            line.push(child.value(&self.0).unwrap().san.to_string());
+            result.push(line.clone());
+            self.lines_from_node(child, line, result);
+            line.pop();
+        }
+    }
+
+    /// Writes the tree as PGN, with the tags, NAGs and comments it was read with. `annotations` are
+    /// added after the moves, by the id of the line up to the move (see `Variation::id`), e.g. NAGs or comments
+    pub fn to_pgn(&self, annotations: &std::collections::HashMap<String, String>) -> String {
+        let mut pgn = String::new();
+        for (name, value) in &self.2.tags {
+            pgn += &format!("[{} \"{}\"]\n", name, value);
+        }
+        match self.1 {
+            Some(shakmaty::Color::White) => pgn += "[Orientation \"white\"]\n",
+            Some(shakmaty::Color::Black) => pgn += "[Orientation \"black\"]\n",
+            None => {}
+        }
+        if !pgn.is_empty() {
+            pgn += "\n";
+        }
+
+        if let Some(remarks) = self.2.remarks.get("") {
+            for comment in &remarks.comments {
+                pgn += &format!("{{{}}} ", comment);
+            }
+        }
+
+        let mut line = Vec::new();
+        self.write_pgn(&self.0.root, &mut line, true, annotations, &mut pgn);
+        pgn.trim_end().to_string() + "\n"
+    }
+
+    /// Writes the moves after `node`. The first child continues the line, the others are written
+    /// as variations in between. Black's moves only get a number (`number`) at the start of a
+    /// variation or after a variation or comment
+    fn write_pgn(&self, node: &Rc<Node<'source>>, line: &mut Vec<String>, number: bool, annotations: &std::collections::HashMap<String, String>, pgn: &mut String) {
+        let children = node.get_children(&self.0);
+        let main = match children.first() {
+            Some(main) => main,
+            None => return
+        };
+
+        let commented = self.write_move(main, line, number, annotations, pgn);
+        for child in &children[1..] {
+            *pgn += "(";
+            let commented = self.write_move(child, line, true, annotations, pgn);
+            line.push(child.value(&self.0).unwrap().san.to_string());
+            self.write_pgn(child, line, commented, annotations, pgn);
+            line.pop();
+            *pgn = pgn.trim_end().to_string() + ") ";
+        }
+
+        line.push(main.value(&self.0).unwrap().san.to_string());
+        self.write_pgn(main, line, commented || children.len() > 1, annotations, pgn);
+        line.pop();
+    }
+
+    /// Writes the move after `line`, with its number if white plays it or `number` is set.
+    /// Returns whether a comment follows
+    fn write_move(&self, node: &Rc<Node<'source>>, line: &[String], number: bool, annotations: &std::collections::HashMap<String, String>, pgn: &mut String) -> bool {
+        let value = node.value(&self.0).unwrap();
+        let ply = line.len();
+        if ply % 2 == 0 {
+            *pgn += &format!("{}. ", ply / 2 + 1);
+        } else if number {
+            *pgn += &format!("{}... ", ply / 2 + 1);
+        }
+
+        let mut id: Vec<&str> = line.iter().map(|san| san.as_str()).collect();
+        id.push(&value.san);
+        let id = id.join(" ");
+        let remarks = self.2.remarks.get(&id);
+        let nags = remarks.map_or(&[][..], |remarks| &remarks.nags[..]);
+
+        *pgn += &value.san;
+        if nags.contains(&3) {
+            *pgn += "!!";
+        } else if value.preferred {
+            *pgn += "!";
+        }
+        *pgn += " ";
+        for nag in nags.iter().filter(|&&nag| nag != 3) {
+            *pgn += &format!("${} ", nag);
+        }
+
+        let mut commented = false;
+        if let Some(weight) = value.weight {
+            *pgn += &format!("{{[%weight {}]}} ", weight);
+            commented = true;
+        }
+        for comment in remarks.iter().flat_map(|remarks| &remarks.comments) {
+            *pgn += &format!("{{{}}} ", comment);
+            commented = true;
+        }
+
+        if let Some(annotation) = annotations.get(&id) {
+            *pgn += annotation;
+            *pgn += " ";
+            commented = true;
+        }
+
+        commented
+    }
+
+    fn line_id(&self, nodes: &[Rc<Node<'source>>]) -> String {
+        let moves: Vec<String> = nodes.iter().map(|node| node.value(&self.0).unwrap().san.to_string()).collect();
+        moves.join(" ")
+    }
+
     fn resolve_variation_internal(&self, node: Rc<Node<'source>>, result: &'_ mut Vec<Rc<Node<'source>>>) {
         if let Some(parent) = node.try_get_parent(&self.0) {
             self.resolve_variation_internal(parent, result);
@@ -91,18 +355,24 @@ impl<'source> Variation<'source> {
         Self { tree, node }
     }
 
-    fn resolve_nodes(&self) -> Vec<Rc<Node<'source>>> {
-        let count = {
-            let mut count = 0;
-            let mut node = self.node.clone();
+    /// Number of moves of the variation
+    pub fn len(&self) -> usize {
+        let mut count = 0;
+        let mut node = self.node.clone();
+        while let Some(parent) = node.try_get_parent(&self.tree.0) {
+            count += 1;
+            node = parent;
+        }
 
-            while let Some(parent) = node.try_get_parent(&self.tree.0) {
-                count = count + 1;
-                node = parent;
-            }
+        count
+    }
 
-            count
-        };
+    pub fn is_empty(&self) -> bool {
+        self.node.try_get_parent(&self.tree.0).is_none()
+    }
+
+    fn resolve_nodes(&self) -> Vec<Rc<Node<'source>>> {
+        let count = self.len();
 
         let mut nodes = Vec::new();
         nodes.reserve_exact(count);
@@ -112,9 +382,27 @@ impl<'source> Variation<'source> {
         nodes
     }
 
+    /// The chance to get to the end of the variation if at each position one of the moves is
+    /// picked by its weight, see `VariationIterator::peek_all_weighted`. If all moves of a position
+    /// have weight 0, each is picked with the same chance
+    pub fn probability(&self) -> f64 {
+        let weight = |node: &Rc<Node<'source>>| node.value(&self.tree.0).unwrap().weight.unwrap_or(1) as f64;
+        self.resolve_nodes().iter().map(|node| {
+            let parent = node.try_get_parent(&self.tree.0).unwrap();
+            let children = parent.get_children(&self.tree.0);
+            let total: f64 = children.iter().map(weight).sum();
+            if total == 0.0 { 1.0 / children.len() as f64 } else { weight(node) / total }
+        }).product()
+    }
+
+    /// A stable id of the variation: its moves in SAN, separated by spaces
+    pub fn id(&self) -> String {
+        self.tree.line_id(&self.resolve_nodes())
+    }
+
     #[cfg(test)]
-    pub fn resolve(&self) -> Vec<&'source str> {
-        self.resolve_nodes().iter().map(|node| node.value(&self.tree.0).unwrap()).collect()
+    pub fn resolve(&self) -> Vec<String> {
+        self.resolve_nodes().iter().map(|node| node.value(&self.tree.0).unwrap().san.to_string()).collect()
     }
 
     pub fn iter(&self) -> VariationIterator<'source> {
@@ -133,13 +421,16 @@ impl<'source> Variations<'source> {
         Variation::new(self.tree.clone(), self.variations.choose(rng).unwrap().clone())
     }
 
-    #[cfg(test)]
     #[inline]
     pub fn len(&self) -> usize {
         self.variations.len()
     }
 
-    #[cfg(test)]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.variations.is_empty()
+    }
+
     pub fn get(&self, index: usize) -> Variation<'source> {
         Variation::new(self.tree.clone(), self.variations[index].clone())
     }
@@ -161,7 +452,7 @@ impl<'source, 'a> Iterator for VariationIterator<'source> {
             Some(node) => {
                 self.index = self.index + 1;
 
-                let m = node.value(&self.tree.0).unwrap();
+                let m = node.value(&self.tree.0).unwrap().san;
                 let san: shakmaty::san::San = m.parse().unwrap();
                 let m = san.to_move(&self.pos).unwrap();
     
@@ -180,7 +471,7 @@ impl<'source> VariationIterator<'source> {
         match self.nodes.get(self.index) {
             None => None,
             Some(node) => {
-                let m = node.value(&self.tree.0).unwrap();
+                let m = node.value(&self.tree.0).unwrap().san;
                 let san: shakmaty::san::San = m.parse().unwrap();
                 let m = san.to_move(&self.pos).unwrap();
 
@@ -196,7 +487,7 @@ impl<'source> VariationIterator<'source> {
             Some(node) => {
                 let parent = node.try_get_parent(&self.tree.0).unwrap();
                 parent.get_children(&self.tree.0).iter().map(|node| {
-                    let m = node.value(&self.tree.0).unwrap();
+                    let m = node.value(&self.tree.0).unwrap().san;
                     let san: shakmaty::san::San = m.parse().unwrap();
                     san.to_move(&self.pos).unwrap()
                 }).collect()
@@ -204,17 +495,81 @@ impl<'source> VariationIterator<'source> {
         }
     }
 
+    /// Like `peek_all`, but also counts the variations that start with each move
+    pub fn peek_all_counted(&self) -> Vec<(shakmaty::Move, usize)> {
+        match self.nodes.get(self.index) {
+            None => Vec::new(),
+            Some(node) => {
+                let parent = node.try_get_parent(&self.tree.0).unwrap();
+                parent.get_children(&self.tree.0).iter().map(|node| {
+                    let m = node.value(&self.tree.0).unwrap().san;
+                    let san: shakmaty::san::San = m.parse().unwrap();
+                    (san.to_move(&self.pos).unwrap(), node.count_leaves(&self.tree.0))
+                }).collect()
+            }
+        }
+    }
+
+    /// Like `peek_all`, but with the weight of each move. Moves without a weight have weight 1
+    pub fn peek_all_weighted(&self) -> Vec<(shakmaty::Move, u32)> {
+        match self.nodes.get(self.index) {
+            None => Vec::new(),
+            Some(node) => {
+                let parent = node.try_get_parent(&self.tree.0).unwrap();
+                parent.get_children(&self.tree.0).iter().map(|node| {
+                    let value = node.value(&self.tree.0).unwrap();
+                    let san: shakmaty::san::San = value.san.parse().unwrap();
+                    (san.to_move(&self.pos).unwrap(), value.weight.unwrap_or(1))
+                }).collect()
+            }
+        }
+    }
+
+    /// The move marked as preferred among all moves from the current position, if any
+    pub fn preferred(&self) -> Option<shakmaty::Move> {
+        let node = self.nodes.get(self.index)?;
+        let parent = node.try_get_parent(&self.tree.0).unwrap();
+        parent.get_children(&self.tree.0).iter().find_map(|node| {
+            let value = node.value(&self.tree.0).unwrap();
+            if !value.preferred {
+                return None;
+            }
+
+            let san: shakmaty::san::San = value.san.parse().unwrap();
+            Some(san.to_move(&self.pos).unwrap())
+        })
+    }
+
     pub fn reset(&mut self) {
         self.index  = 0;
         self.pos = Default::default();
     }
 
+    /// Takes back the last move. Returns false if we are already at the start of the variation
+    pub fn back(&mut self) -> bool {
+        if self.index == 0 {
+            return false;
+        }
+
+        // Positions cannot be undone, so replay the variation up to the previous move
+        let index = self.index - 1;
+        self.reset();
+        self.fast_forward(index);
+
+        true
+    }
+
+    /// Plays the moves of the variation up to the ply, or up to its end if it is shorter
+    pub fn fast_forward(&mut self, ply: usize) {
+        while self.index < ply && self.next().is_some() {}
+    }
+
     fn try_switch_internal(&mut self, m: &shakmaty::Move) -> Option<()> {
         let node = self.nodes.get(self.index)?;
         let parent = node.try_get_parent(&self.tree.0).unwrap();
 
         for child in parent.get_children(&self.tree.0) {
-            let found_move = child.value(&self.tree.0).unwrap();
+            let found_move = child.value(&self.tree.0).unwrap().san;
             let san: shakmaty::san::San = found_move.parse().unwrap();
             let found_move = san.to_move(&self.pos).unwrap();
 
@@ -236,6 +591,16 @@ impl<'source> VariationIterator<'source> {
     pub fn position(&self) -> &shakmaty::Chess {
         &self.pos
     }
+
+    /// Number of moves played so far
+    pub fn ply(&self) -> usize {
+        self.index
+    }
+
+    /// The id of the variation currently followed, see `Variation::id`
+    pub fn line_id(&self) -> String {
+        self.tree.line_id(&self.nodes)
+    }
 }
 
 #[cfg(test)]
@@ -307,4 +672,120 @@ mod tests {
             assert_eq!(variations.get(2).resolve(), ["d4", "d5", "c4"]);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn iterate_back_and_forth() {
+        let mut tree = super::MoveTree::new();
+        tree.add_pgn("e4 e5 Nf3 Nf6");
+        let variations = std::rc::Rc::new(tree).get_all_variations();
+
+        let mut iter = variations.get(0).iter();
+        assert!(!iter.back());
+
+        iter.next();
+        iter.next();
+        let after_e5 = shakmaty::fen::fen(iter.position());
+        let nf3 = iter.next().unwrap();
+
+        assert!(iter.back());
+        assert_eq!(shakmaty::fen::fen(iter.position()), after_e5);
+        assert_eq!(iter.peek(), Some(nf3.clone()));
+        assert_eq!(iter.next(), Some(nf3));
+
+        assert!(iter.back());
+        assert!(iter.back());
+        assert!(iter.back());
+        assert!(!iter.back());
+        assert_eq!(shakmaty::fen::fen(iter.position()), shakmaty::fen::fen(&shakmaty::Chess::default()));
+
+        assert_eq!(variations.get(0).len(), 4);
+        iter.fast_forward(2);
+        assert_eq!(shakmaty::fen::fen(iter.position()), after_e5);
+        iter.fast_forward(10);
+        assert_eq!(iter.ply(), 4);
+        assert_eq!(iter.peek(), None);
+    }
+
+    #[test]
+    fn pgn_with_tags() {
+        let mut tree = super::MoveTree::new();
+        tree.add_pgn("[Event \"Repertoire\"]\n[Orientation \"black\"]\n\n1. e4 e5 2. Nf3\n");
+        assert_eq!(tree.orientation(), Some(shakmaty::Color::Black));
+        {
+            let tree = std::rc::Rc::new(tree);
+            let variations = tree.get_all_variations();
+            assert_eq!(variations.len(), 1);
+
+            assert_eq!(variations.get(0).resolve(), ["e4", "e5", "Nf3"]);
+        }
+
+        let mut tree = super::MoveTree::new();
+        tree.add_pgn("e4 e5");
+        assert_eq!(tree.orientation(), None);
+    }
+
+    #[test]
+    fn pgn_with_annotations() {
+        let mut tree = super::MoveTree::new();
+        tree.add_pgn("1. e4 e5 2. Nf3! (2. Nc3 Nf6) (2. Bc4?! Nf6) 2... Nc6 $1 (2... Nf6)");
+        let variations = std::rc::Rc::new(tree).get_all_variations();
+        assert_eq!(variations.len(), 4);
+        assert_eq!(variations.get(0).resolve(), ["e4", "e5", "Nf3", "Nc6"]);
+
+        let mut iter = variations.get(2).iter();
+        assert_eq!(iter.preferred(), None);
+        iter.next();
+        iter.next();
+        let nf3 = iter.preferred().unwrap();
+        assert_eq!(nf3.to(), shakmaty::Square::F3);
+        assert_ne!(iter.peek(), Some(nf3.clone()));
+
+        assert!(iter.try_switch(&nf3));
+        iter.next();
+        assert_eq!(iter.preferred().unwrap().to(), shakmaty::Square::C6);
+    }
+
+    #[test]
+    fn pgn_with_weights() {
+        let mut tree = super::MoveTree::new();
+        tree.add_pgn("1. e4 {[%weight 80]} (1. d4 {Queen's pawn [%weight 15]} d5) (1. c4) e5");
+        let variations = std::rc::Rc::new(tree).get_all_variations();
+        assert_eq!(variations.len(), 3);
+
+        let weights: Vec<u32> = variations.get(0).iter().peek_all_weighted().into_iter().map(|(_, weight)| weight).collect();
+        assert_eq!(weights, [80, 15, 1]);
+        assert!((variations.get(0).probability() - 80.0 / 96.0).abs() < 1e-9);
+        assert!((variations.get(2).probability() - 1.0 / 96.0).abs() < 1e-9);
+
+        let mut tree = super::MoveTree::new();
+        tree.add_pgn("1. e4 {[%weight 0]} (1. d4 {[%weight 0]}) e5");
+        let variations = std::rc::Rc::new(tree).get_all_variations();
+        assert!((variations.get(1).probability() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn write_pgn() {
+        let pgn = "[Orientation \"black\"]\n\n1. e4 e5 2. Nf3! {[%weight 3]} (2. Nc3 Nf6) 2... Nc6 (2... Nf6 3. Nxe5) 3. Bb5\n";
+        let mut tree = super::MoveTree::new();
+        tree.add_pgn(pgn);
+        assert_eq!(tree.to_pgn(&Default::default()), pgn);
+
+        let lines = tree.lines();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[5], ["e4", "e5", "Nf3", "Nf6"]);
+
+        // A line added from a PGN which is dropped right away
+        tree.add_owned_pgn(&String::from("1. e4 e5 2. Bc4"));
+        assert_eq!(tree.lines().last().unwrap(), &["e4", "e5", "Bc4"]);
+
+        let mut annotations = std::collections::HashMap::new();
+        annotations.insert("e4 e5 Nc3".to_string(), "$2 {Zu passiv}".to_string());
+        assert!(tree.to_pgn(&annotations).contains("(2. Nc3 $2 {Zu passiv} 2... Nf6)"));
+
+        // Tags, NAGs and comments are written back
+        let pgn = "[Event \"Repertoire\"]\n[Orientation \"white\"]\n\n{Open games} 1. e4!! $14 {Best by test} e5 2. Nf3 {Main line [%weight 3]} Nc6?!\n";
+        let mut tree = super::MoveTree::new();
+        tree.add_pgn(pgn);
+        assert_eq!(tree.to_pgn(&Default::default()), "[Event \"Repertoire\"]\n[Orientation \"white\"]\n\n{Open games} 1. e4!! $14 {Best by test} 1... e5 2. Nf3 {[%weight 3]} {Main line} 2... Nc6 $6\n");
+    }
+}
@@ -82,6 +82,16 @@ impl<T> Node<T> {
         &self.inner.ro(&tree.owner).children
     }
 
+    /// Number of leaves below this node, i.e. the number of variations passing through it
+    pub fn count_leaves(&self, tree: &Tree<T>) -> usize {
+        let children = self.get_children(tree);
+        if children.is_empty() {
+            1
+        } else {
+            children.iter().map(|child| child.count_leaves(tree)).sum()
+        }
+    }
+
     pub fn find_first_leaf<'a>(self: &'a Rc<Self>, tree: &'a Tree<T>) -> &'a Rc<Self> {
         let mut node = self;
         loop {
@@ -123,8 +133,12 @@ impl<T: PartialEq> Node<T> {
     }
 }
 
-impl<T: Copy> Node<T> {
+impl<T: Clone> Node<T> {
     pub fn value(&self, tree: &Tree<T>) -> Option<T> {
-        self.inner.ro(&tree.owner).value
+        self.inner.ro(&tree.owner).value.clone()
+    }
+
+    pub fn set_value(&self, tree: &mut Tree<T>, value: T) {
+        self.inner.rw(&mut tree.owner).value = Some(value);
     }
 }
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+const DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// When a forgotten line is due again. Until then, other lines (e.g. new ones) are trained
+const RELEARN: f64 = 10.0 * 60.0 * 1000.0;
+
+/// How a line went the last time it was played
+#[derive(Clone, Copy, Default)]
+pub struct Performance {
+    pub errors: u32,
+    /// Number of times the move was shown
+    pub hints: u32,
+    /// Number of times a hint was made more precise after wrong moves, see `trainer::HintPolicy`
+    pub escalations: u32
+}
+
+impl Performance {
+    /// Quality of the answer on the SM-2 scale from 0 to 5. A line played with hints is treated
+    /// as not remembered, even if there were no wrong moves
+    pub fn quality(&self) -> u8 {
+        if self.hints > 0 {
+            return 2;
+        }
+
+        if self.escalations > 0 {
+            return 3;
+        }
+
+        match self.errors {
+            0 => 5,
+            1 => 4,
+            _ => 3
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Card {
+    pub repetitions: u32,
+    pub ease: f64,
+    /// in days
+    pub interval: f64,
+    /// in milliseconds since the epoch, see `util::now`
+    pub due: f64
+}
+
+impl Card {
+    fn new() -> Self {
+        Card {
+            repetitions: 0,
+            ease: 2.5,
+            interval: 0.0,
+            due: 0.0
+        }
+    }
+
+    fn review(&mut self, quality: u8, now: f64) {
+        if quality >= 3 {
+            self.interval = match self.repetitions {
+                0 => 1.0,
+                1 => 6.0,
+                _ => self.interval * self.ease
+            };
+            self.repetitions += 1;
+        } else {
+            // Forgotten lines have to be learned again in this session, but not right away,
+            // so they don't keep all other lines from being trained
+            self.repetitions = 0;
+            self.interval = 0.0;
+        }
+
+        let q = (5 - quality) as f64;
+        self.ease = (self.ease + 0.1 - q * (0.08 + q * 0.02)).max(1.3);
+        self.due = now + if self.interval == 0.0 { RELEARN } else { self.interval * DAY };
+    }
+}
+
+/// SM-2 scheduler for lines, keyed by their line id
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Scheduler {
+    cards: HashMap<String, Card>
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Card> {
+        self.cards.get(id)
+    }
+
+    pub fn review(&mut self, id: &str, performance: Performance, now: f64) {
+        self.cards.entry(id.to_string()).or_insert_with(Card::new).review(performance.quality(), now);
+    }
+
+    /// Reviews a line which was trained from a later position. As its first moves weren't asked,
+    /// it counts at best as recalled with difficulty
+    pub fn review_partial(&mut self, id: &str, performance: Performance, now: f64) {
+        self.cards.entry(id.to_string()).or_insert_with(Card::new).review(performance.quality().min(3), now);
+    }
+
+    /// Picks the index of the line to train next: overdue lines first (the longest overdue one),
+    /// then lines which were never trained, then the line which will be due next.
+    /// `current` is only chosen if it is the only line.
+    pub fn choose<'a>(&self, ids: impl IntoIterator<Item=&'a str>, current: Option<&str>, now: f64) -> Option<usize> {
+        let mut best: Option<(usize, (u8, f64))> = None;
+        let mut fallback = None;
+
+        for (index, id) in ids.into_iter().enumerate() {
+            if Some(id) == current {
+                fallback = Some(index);
+                continue;
+            }
+
+            let key = match self.cards.get(id) {
+                Some(card) if card.due <= now => (0, card.due),
+                None => (1, 0.0),
+                Some(card) => (2, card.due)
+            };
+
+            let better = match best {
+                None => true,
+                Some((_, best_key)) => key.0 < best_key.0 || (key.0 == best_key.0 && key.1 < best_key.1)
+            };
+
+            if better {
+                best = Some((index, key));
+            }
+        }
+
+        best.map(|(index, _)| index).or(fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Performance, Scheduler, DAY, RELEARN};
+
+    const PERFECT: Performance = Performance { errors: 0, hints: 0, escalations: 0 };
+    const HINTED: Performance = Performance { errors: 3, hints: 1, escalations: 3 };
+
+    #[test]
+    fn intervals_grow_with_good_answers() {
+        let mut scheduler = Scheduler::new();
+        scheduler.review("a", PERFECT, 0.0);
+        assert_eq!(scheduler.get("a").unwrap().due, DAY);
+
+        scheduler.review("a", PERFECT, DAY);
+        assert_eq!(scheduler.get("a").unwrap().due, 7.0 * DAY);
+
+        scheduler.review("a", PERFECT, 7.0 * DAY);
+        let card = scheduler.get("a").unwrap();
+        assert!(card.interval > 6.0);
+        assert!(card.ease > 2.5);
+
+        scheduler.review("a", HINTED, 20.0 * DAY);
+        let card = scheduler.get("a").unwrap();
+        assert_eq!(card.repetitions, 0);
+        assert_eq!(card.due, 20.0 * DAY + RELEARN);
+
+        // Part of a line is no proof of knowing all of it
+        scheduler.review_partial("b", PERFECT, 0.0);
+        let card = scheduler.get("b").unwrap();
+        assert_eq!(card.due, DAY);
+        assert!(card.ease < 2.5);
+    }
+
+    #[test]
+    fn choose_prefers_due_lines() {
+        let mut scheduler = Scheduler::new();
+        scheduler.review("known", PERFECT, 0.0);
+        scheduler.review("forgotten", HINTED, 0.0);
+
+        let ids = ["known", "new", "forgotten"];
+        let now = RELEARN + 1.0;
+        assert_eq!(scheduler.choose(ids.iter().copied(), None, now), Some(2));
+        assert_eq!(scheduler.choose(ids.iter().copied(), Some("forgotten"), now), Some(1));
+        assert_eq!(scheduler.choose(ids[..1].iter().copied(), None, now), Some(0));
+        assert_eq!(scheduler.choose(ids[..1].iter().copied(), Some("known"), now), Some(0));
+        assert_eq!(scheduler.choose(std::iter::empty(), None, now), None);
+
+        // A line forgotten just now waits for the new one
+        assert_eq!(scheduler.choose(ids.iter().copied(), None, 1.0), Some(1));
+    }
+
+    #[test]
+    fn every_line_gets_trained() {
+        let ids = ["a", "b", "c", "d"];
+        for performance in [PERFECT, HINTED] {
+            let mut scheduler = Scheduler::new();
+            let mut current = None;
+            let mut trained = Vec::new();
+
+            // A line a minute
+            for minute in 0..ids.len() {
+                let now = minute as f64 * 60.0 * 1000.0;
+                let index = scheduler.choose(ids.iter().copied(), current, now).unwrap();
+                scheduler.review(ids[index], performance, now);
+                current = Some(ids[index]);
+                trained.push(ids[index]);
+            }
+
+            trained.sort();
+            assert_eq!(trained, ids);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::coverage::{Coverage, Order};
+use crate::levels::Levels;
+use crate::schedule::Scheduler;
+use crate::statistics::Statistics;
+
+/// Picks the line to train next, as index into `ids` (the line ids, see `Variation::id`).
+/// `current` is the line trained last, which is only picked again if there is no other
+pub trait LineSelector {
+    fn choose(&self, ids: &[&str], current: Option<&str>, rng: &mut dyn rand::RngCore) -> Option<usize>;
+}
+
+/// The indices of all lines but `current`, unless it is the only one
+fn candidates(ids: &[&str], current: Option<&str>) -> Vec<usize> {
+    let others: Vec<usize> = (0..ids.len()).filter(|&index| Some(ids[index]) != current).collect();
+    if others.is_empty() { (0..ids.len()).collect() } else { others }
+}
+
+/// Any line, with the same chance
+pub struct Random;
+
+impl LineSelector for Random {
+    fn choose(&self, ids: &[&str], current: Option<&str>, rng: &mut dyn rand::RngCore) -> Option<usize> {
+        use rand::seq::SliceRandom;
+        candidates(ids, current).choose(rng).copied()
+    }
+}
+
+/// The lines in the order of the PGN, starting again after the last one
+pub struct Sequential;
+
+impl LineSelector for Sequential {
+    fn choose(&self, ids: &[&str], current: Option<&str>, _rng: &mut dyn rand::RngCore) -> Option<usize> {
+        if ids.is_empty() {
+            return None;
+        }
+
+        match ids.iter().position(|&id| Some(id) == current) {
+            Some(index) => Some((index + 1) % ids.len()),
+            None => Some(0)
+        }
+    }
+}
+
+/// Lines when they are due, see `Scheduler::choose`
+pub struct SpacedRepetition<'a> {
+    pub scheduler: &'a Scheduler,
+    /// see `util::now`
+    pub now: f64
+}
+
+impl<'a> LineSelector for SpacedRepetition<'a> {
+    fn choose(&self, ids: &[&str], current: Option<&str>, _rng: &mut dyn rand::RngCore) -> Option<usize> {
+        self.scheduler.choose(ids.iter().copied(), current, self.now)
+    }
+}
+
+/// The line with the most errors and hints per time it was played. Lines which were never played
+/// come first, as nothing is known about them
+pub struct WeakestFirst<'a>(pub &'a Statistics);
+
+impl<'a> LineSelector for WeakestFirst<'a> {
+    fn choose(&self, ids: &[&str], current: Option<&str>, _rng: &mut dyn rand::RngCore) -> Option<usize> {
+        let weakness = |index: usize| match self.0.get(ids[index]) {
+            Some(line) if line.played > 0 => (line.errors + line.hints) as f64 / line.played as f64,
+            _ => f64::INFINITY
+        };
+
+        // The first of the weakest lines
+        candidates(ids, current).into_iter().fold(None, |best: Option<(usize, f64)>, index| {
+            let weakness = weakness(index);
+            match best {
+                Some((_, best_weakness)) if best_weakness >= weakness => best,
+                _ => Some((index, weakness))
+            }
+        }).map(|(index, _)| index)
+    }
+}
+
+/// Lines at random, with a chance proportional to their weight (one per line, see `Variation::probability`).
+/// If all weights are 0, any line has the same chance
+pub struct Weighted<'a>(pub &'a [f64]);
+
+impl<'a> LineSelector for Weighted<'a> {
+    fn choose(&self, ids: &[&str], current: Option<&str>, rng: &mut dyn rand::RngCore) -> Option<usize> {
+        use rand::distributions::Distribution;
+        let candidates = candidates(ids, current);
+        match rand::distributions::WeightedIndex::new(candidates.iter().map(|&index| self.0[index])) {
+            Ok(distribution) => Some(candidates[distribution.sample(rng)]),
+            Err(_) => Random.choose(ids, current, rng)
+        }
+    }
+}
+
+/// The lines which are not mastered yet, see `Coverage::choose`
+pub struct Unmastered<'a> {
+    pub coverage: &'a Coverage,
+    pub order: Order
+}
+
+impl<'a> LineSelector for Unmastered<'a> {
+    fn choose(&self, ids: &[&str], current: Option<&str>, _rng: &mut dyn rand::RngCore) -> Option<usize> {
+        self.coverage.choose(ids, self.order, current)
+    }
+}
+
+/// The lines of the given level which are not mastered yet
+pub struct CurrentLevel<'a> {
+    pub levels: Levels,
+    pub level: usize,
+    pub coverage: &'a Coverage
+}
+
+impl<'a> LineSelector for CurrentLevel<'a> {
+    fn choose(&self, ids: &[&str], current: Option<&str>, _rng: &mut dyn rand::RngCore) -> Option<usize> {
+        let lines = self.levels.lines(self.level);
+        let level_ids: Vec<&str> = lines.iter().map(|&index| ids[index]).collect();
+        self.coverage.choose(&level_ids, Order::Tree, current).map(|index| lines[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineSelector, Random, Sequential, WeakestFirst, Weighted};
+    use crate::schedule::Performance;
+    use crate::statistics::Statistics;
+
+    const IDS: [&str; 3] = ["e4 e5 Nf3", "e4 e5 Nc3", "d4 d5"];
+
+    #[test]
+    fn selectors_skip_the_current_line() {
+        let mut rng = rand::thread_rng();
+
+        assert_eq!(Sequential.choose(&IDS, None, &mut rng), Some(0));
+        assert_eq!(Sequential.choose(&IDS, Some(IDS[2]), &mut rng), Some(0));
+        assert_eq!(Sequential.choose(&[], None, &mut rng), None);
+
+        for _ in 0..10 {
+            assert_ne!(Random.choose(&IDS, Some(IDS[1]), &mut rng), Some(1));
+            assert_eq!(Weighted(&[1.0, 0.0, 1.0]).choose(&IDS, Some(IDS[2]), &mut rng), Some(0));
+            assert_ne!(Weighted(&[0.0, 0.0, 1.0]).choose(&IDS, Some(IDS[2]), &mut rng), None);
+        }
+        assert_eq!(Random.choose(&IDS[..1], Some(IDS[0]), &mut rng), Some(0));
+
+        let mut statistics = Statistics::new();
+        for id in &IDS {
+            statistics.record(id, &Performance::default());
+        }
+        statistics.record(IDS[1], &Performance { errors: 2, hints: 0, escalations: 0 });
+        assert_eq!(WeakestFirst(&statistics).choose(&IDS, None, &mut rng), Some(1));
+        assert_eq!(WeakestFirst(&statistics).choose(&IDS, Some(IDS[1]), &mut rng), Some(0));
+    }
+}
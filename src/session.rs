@@ -0,0 +1,115 @@
+/// One attempt at a line in the current session
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct LineRecord {
+    /// The line id, see `Variation::id`
+    pub line: String,
+    /// Number of moves the student answered
+    pub moves: u32,
+    /// Moves answered correctly on the first try
+    pub first_try: u32,
+    pub hints: u32,
+    /// Time spent on the answered moves, in milliseconds
+    pub time: f64,
+    pub completed: bool
+}
+
+impl LineRecord {
+    /// Average time per answered move in milliseconds
+    pub fn time_per_move(&self) -> f64 {
+        if self.moves == 0 { 0.0 } else { self.time / self.moves as f64 }
+    }
+}
+
+/// What happened since the trainer was started. Unlike `statistics`, this is not saved
+#[derive(Clone, Default, Debug)]
+pub struct Session {
+    lines: Vec<LineRecord>
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Starts a new attempt. The previous one is dropped if nothing happened in it
+    pub fn start_line(&mut self, line: &str) {
+        if self.lines.last().is_some_and(|last| last.moves == 0 && last.hints == 0 && !last.completed) {
+            self.lines.pop();
+        }
+
+        self.lines.push(LineRecord { line: line.to_string(), ..Default::default() });
+    }
+
+    pub fn record_move(&mut self, first_try: bool, time: f64) {
+        if let Some(record) = self.lines.last_mut() {
+            record.moves += 1;
+            record.first_try += first_try as u32;
+            record.time += time;
+        }
+    }
+
+    pub fn record_hint(&mut self) {
+        if let Some(record) = self.lines.last_mut() {
+            record.hints += 1;
+        }
+    }
+
+    /// The line may have changed since it was started, when the student chose another move of the repertoire
+    pub fn complete_line(&mut self, line: &str) {
+        if let Some(record) = self.lines.last_mut() {
+            record.line = line.to_string();
+            record.completed = true;
+        }
+    }
+
+    pub fn lines(&self) -> &[LineRecord] {
+        &self.lines
+    }
+
+    /// The totals over all lines. `completed` tells whether every line was completed
+    pub fn total(&self) -> LineRecord {
+        let mut total = LineRecord { completed: true, ..Default::default() };
+        for record in &self.lines {
+            total.moves += record.moves;
+            total.first_try += record.first_try;
+            total.hints += record.hints;
+            total.time += record.time;
+            total.completed &= record.completed;
+        }
+
+        total
+    }
+
+    pub fn completed(&self) -> usize {
+        self.lines.iter().filter(|record| record.completed).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Session;
+
+    #[test]
+    fn counts_attempts() {
+        let mut session = Session::new();
+        session.start_line("e4 e5 Nf3");
+        session.record_move(true, 1000.0);
+        session.record_hint();
+        session.record_move(false, 3000.0);
+        session.complete_line("e4 e5 Nf3 Nf6");
+
+        // Restarted without playing a move
+        session.start_line("e4 e5 Nc3");
+        session.start_line("e4 e5 Nc3");
+        session.record_move(true, 500.0);
+
+        assert_eq!(session.lines().len(), 2);
+        assert_eq!(session.completed(), 1);
+        assert_eq!(session.lines()[0].line, "e4 e5 Nf3 Nf6");
+        assert_eq!(session.lines()[0].time_per_move(), 2000.0);
+
+        let total = session.total();
+        assert_eq!((total.moves, total.first_try, total.hints), (3, 2, 1));
+        assert_eq!(total.time_per_move(), 1500.0);
+    }
+}
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+use crate::schedule::Performance;
+
+/// Totals over all the times a line was played
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct LineStatistics {
+    pub played: u32,
+    pub errors: u32,
+    pub hints: u32,
+    pub escalations: u32
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Statistics {
+    lines: HashMap<String, LineStatistics>
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&LineStatistics> {
+        self.lines.get(id)
+    }
+
+    pub fn record(&mut self, id: &str, performance: &Performance) {
+        let line = self.lines.entry(id.to_string()).or_default();
+        line.played += 1;
+        line.errors += performance.errors;
+        line.hints += performance.hints;
+        line.escalations += performance.escalations;
+    }
+}
@@ -0,0 +1,134 @@
+/// Key value store for everything which should survive a reload
+pub trait Storage {
+    fn get(&self, key: &str) -> Option<String>;
+    /// Returns false if the value could not be stored, e.g. because the storage is full
+    fn set(&self, key: &str, value: &str) -> bool;
+}
+
+pub fn load<T: serde::de::DeserializeOwned>(storage: &dyn Storage, key: &str) -> Option<T> {
+    // Entries we cannot read anymore (e.g. written by an older version) are ignored
+    serde_json::from_str(&storage.get(key)?).ok()
+}
+
+/// Returns false if the value could not be stored, see `Storage::set`
+pub fn save<T: serde::Serialize>(storage: &dyn Storage, key: &str, value: &T) -> bool {
+    storage.set(key, &serde_json::to_string(value).unwrap())
+}
+
+/// The browser's `localStorage`. If it is not available (e.g. disabled by the user),
+/// nothing is stored. Keys are prefixed, as other pages on the same origin share the storage
+pub struct LocalStorage(Option<web_sys::Storage>);
+
+impl LocalStorage {
+    pub fn new() -> Self {
+        LocalStorage(web_sys::window().and_then(|window| window.local_storage().ok().flatten()))
+    }
+}
+
+impl Default for LocalStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage for LocalStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        self.0.as_ref()?.get_item(&format!("chess-trainer/{}", key)).ok().flatten()
+    }
+
+    /// Browsers allow only a few megabytes per origin
+    fn set(&self, key: &str, value: &str) -> bool {
+        match &self.0 {
+            Some(storage) => storage.set_item(&format!("chess-trainer/{}", key), value).is_ok(),
+            None => false
+        }
+    }
+}
+
+/// A directory with a JSON file per key, for the terminal version
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage(std::path::PathBuf);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    /// The directory is created on the first `set`
+    pub fn new(directory: impl Into<std::path::PathBuf>) -> Self {
+        FileStorage(directory.into())
+    }
+
+    fn path(&self, key: &str) -> std::path::PathBuf {
+        self.0.join(format!("{}.json", key))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        std::fs::read_to_string(self.path(key)).ok()
+    }
+
+    /// Writes to a temporary file first, so a crash never leaves half a file behind
+    fn set(&self, key: &str, value: &str) -> bool {
+        let temporary = self.0.join(format!("{}.json.tmp", key));
+        std::fs::create_dir_all(&self.0).is_ok()
+            && std::fs::write(&temporary, value).is_ok()
+            && std::fs::rename(&temporary, self.path(key)).is_ok()
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryStorage(std::cell::RefCell<std::collections::HashMap<String, String>>);
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        self.0.borrow().get(key).cloned()
+    }
+
+    fn set(&self, key: &str, value: &str) -> bool {
+        self.0.borrow_mut().insert(key.to_string(), value.to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, save, FileStorage, MemoryStorage, Storage};
+    use crate::schedule::{Performance, Scheduler};
+
+    #[test]
+    fn scheduler_round_trip() {
+        let storage = MemoryStorage::new();
+        assert!(load::<Scheduler>(&storage, "schedule").is_none());
+
+        let mut scheduler = Scheduler::new();
+        scheduler.review("e4 e5 Nf3 Nf6", Performance { errors: 1, hints: 0, escalations: 0 }, 1000.0);
+        save(&storage, "schedule", &scheduler);
+
+        let loaded: Scheduler = load(&storage, "schedule").unwrap();
+        let card = loaded.get("e4 e5 Nf3 Nf6").unwrap();
+        assert_eq!(card.repetitions, 1);
+        assert_eq!(card.due, scheduler.get("e4 e5 Nf3 Nf6").unwrap().due);
+
+        storage.set("schedule", "garbage");
+        assert!(load::<Scheduler>(&storage, "schedule").is_none());
+    }
+
+    #[test]
+    fn file_storage() {
+        let directory = std::env::temp_dir().join(format!("chess-trainer-test-{}", std::process::id()));
+        let storage = FileStorage::new(&directory);
+        assert_eq!(storage.get("schedule-white"), None);
+
+        assert!(storage.set("schedule-white", "{}"));
+        assert!(storage.set("schedule-white", "[]"));
+        assert_eq!(FileStorage::new(&directory).get("schedule-white").as_deref(), Some("[]"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,20 +1,423 @@
 use crate::util::DynFuture;
+use crate::coverage::Coverage;
+use crate::engine::{Engine, Score};
+use crate::explorer::{Explorer, Import, MoveStatistics};
+use crate::levels::Levels;
+use crate::mistakes::{Mistake, Mistakes};
+use crate::own_games::{CheckedGame, Departure};
+use crate::schedule::{Performance, Scheduler};
+use crate::selector::{self, LineSelector};
+use crate::session::Session;
+use crate::statistics::Statistics;
+use crate::storage::{self, Storage};
 
 pub trait UI: Clone {
-    fn init(&self, pos: &shakmaty::Chess, explore: bool);
+    fn init(&self, pos: &shakmaty::Chess, explore: bool, orientation: shakmaty::Color);
+    fn set_explore(&self, explore: bool);
+    fn set_exam(&self, exam: bool);
     fn play_move(&self, m: shakmaty::Move, arrows: Vec<crate::components::board::Arrow>);
     fn update_arrows(&self, arrows: Vec<crate::components::board::Arrow>);
+    fn update_labels(&self, labels: Vec<crate::components::board::Label>);
+    fn update_highlights(&self, squares: Vec<shakmaty::Square>);
     fn shake(&self);
     fn get_user_move(&self) -> DynFuture<shakmaty::Move>;
     fn wait_for_user_action(&self) -> DynFuture<UserAction>;
     fn show_hints(&self) -> bool;
+    fn hint_policy(&self) -> HintPolicy;
+    /// Seconds the student has for each move, 0 for no limit. Exam mode is not timed
+    fn time_limit(&self) -> u32;
+    /// Shows the seconds left for the current move, `None` hides the countdown
+    fn show_countdown(&self, seconds: Option<u32>);
+    /// The engine which plays on after the end of a line and suggests moves after mistakes.
+    /// `None` to do without
+    fn engine(&self) -> Option<std::rc::Rc<dyn Engine>>;
+    /// The engine which evaluates every position in explore mode. `None` to do without, e.g. if
+    /// the engine would block the UI on each move
+    fn explore_engine(&self) -> Option<std::rc::Rc<dyn Engine>>;
+    /// Where new lines are started
+    fn start_point(&self) -> StartPoint;
+    /// The seed given by the student, to repeat a session. Otherwise a random seed is used
+    fn seed(&self) -> Option<u64>;
+    fn show_seed(&self, seed: u64);
+    /// Shows the training mode and how many of all lines are mastered. In review mode,
+    /// `total` is the number of mistakes left to review
+    fn show_progress(&self, mode: Mode, mastered: usize, total: usize);
+    /// Shows the current level in level mode, `None` otherwise
+    fn show_level(&self, level: Option<Level>);
+    /// Comments the student's last move, `None` clears the comment
+    fn show_feedback(&self, feedback: Option<Feedback>);
+    /// Shows the wrong moves of a line played in exam mode, once it is finished
+    fn show_exam_result(&self, deviations: Vec<Deviation>);
+    /// Shows what was trained since the start
+    fn show_session(&self, session: Session);
+    /// Offers to add the line played in explore mode, which left the repertoire (see `format_moves`).
+    /// `None` hides the offer
+    fn show_off_book(&self, line: Option<String>);
+    /// Shows how the moves of the position did in the imported games, in explore mode.
+    /// `None` hides the table
+    fn show_explorer(&self, moves: Option<ExplorerMoves>);
+    /// Reports how many games of `UserAction::ImportGames` were imported, and whether they could be saved.
+    /// A big database may not fit into the browser's storage, then it is kept until the page is closed
+    fn show_import(&self, import: Import, saved: bool);
+    /// Shows where the student's own games left the repertoire (see `UserAction::CheckOwnGames`)
+    fn show_checked_games(&self, games: Vec<CheckedGame>);
 }
 
 #[derive(Clone)]
 pub enum UserAction {
     Restart,
+    /// Continues with another line. In level mode this is the next line of the current level,
+    /// or of the next level once the current one is mastered
     NextLevel,
-    ToggleExplore
+    ToggleExplore,
+    /// Leaves explore mode and trains the line followed so far from the current position
+    TrainFromHere,
+    /// Adds the moves played in explore mode which are not in the repertoire, as a new line
+    AddToRepertoire,
+    /// Adds the games of the PGN to the statistics shown in explore mode
+    ImportGames(String),
+    /// Compares the student's games of the PGN with the repertoire and queues the positions where the
+    /// student deviated for review. With a name, only the games where that player has the student's side
+    CheckOwnGames(String, Option<String>),
+    /// Starts the line again in or out of exam mode, where mistakes are only shown at the end
+    ToggleExam,
+    SwitchSide,
+    /// Starts over with the random choices of the seed
+    Reseed(u64),
+    StepBack,
+    StepForward,
+    SetMode(Mode)
+}
+
+/// How the next line is chosen
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Mode {
+    /// Lines are repeated when they are due, see `schedule`
+    #[default]
+    Repetition,
+    /// The lines in the order of the PGN
+    Sequential,
+    /// Any line at random
+    Shuffled,
+    /// The lines with the most mistakes first, see `statistics`
+    WeakestFirst,
+    /// Lines at random, by how likely they are to come up in a game (see `Variation::probability`)
+    Weighted,
+    /// Every line is trained until it is mastered, see `coverage`
+    Coverage(crate::coverage::Order),
+    /// Lines are trained level by level, see `levels`
+    Levels,
+    /// Only the positions of earlier mistakes are trained, see `mistakes`
+    Review,
+    /// The trainer picks each of its replies at random, by the weights of the moves (see `MoveTree`),
+    /// instead of following a line
+    Random
+}
+
+/// Where a new line is started, so that the known first moves don't have to be replayed every time.
+/// The moves before are played without the student
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum StartPoint {
+    #[default]
+    Beginning,
+    /// After the given number of plies
+    Ply(usize),
+    Random
+}
+
+impl StartPoint {
+    /// The ply to start a line of `len` plies from. At least one move of the student is left
+    fn ply(&self, len: usize, student: shakmaty::Color, rng: &mut impl rand::Rng) -> usize {
+        // White moves at even plies
+        let last = match len.checked_sub(1) {
+            Some(last) if (last % 2 == 0) == (student == shakmaty::Color::White) => last,
+            Some(last) => last.saturating_sub(1),
+            None => 0
+        };
+
+        match *self {
+            StartPoint::Beginning => 0,
+            StartPoint::Ply(ply) => ply.min(last),
+            StartPoint::Random => rng.gen_range(0..=last)
+        }
+    }
+}
+
+/// After how many wrong moves the student gets which hint. A threshold of 0 skips the hint
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HintPolicy {
+    /// Highlight the piece to move
+    pub piece: u32,
+    /// Highlight the target square as well
+    pub target: u32,
+    /// Show the move as an arrow
+    pub arrow: u32
+}
+
+impl Default for HintPolicy {
+    fn default() -> Self {
+        HintPolicy { piece: 1, target: 2, arrow: 3 }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum HintStage { Piece, Target, Arrow }
+
+impl HintPolicy {
+    /// The hint to show after exactly this many wrong moves, if any
+    fn stage_after(&self, errors: u32) -> Option<HintStage> {
+        if errors == self.arrow {
+            Some(HintStage::Arrow)
+        } else if errors == self.target {
+            Some(HintStage::Target)
+        } else if errors == self.piece {
+            Some(HintStage::Piece)
+        } else {
+            None
+        }
+    }
+}
+
+/// Comments on a move of the student, or on the position. Scores are from white's point of view
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Feedback {
+    /// The move is in the repertoire, but another move (in SAN) is marked as the main move
+    NotPreferred(String),
+    /// The move the engine would have played (in SAN) instead of a wrong move, with its score
+    EngineMove(String, Score),
+    /// The engine's score of the position in explore mode
+    Evaluation(Score)
+}
+
+/// The moves of a position, with how they did in the imported games
+#[derive(Clone)]
+pub struct ExplorerMoves {
+    /// The side to move, `MoveStatistics::score` is for it
+    pub turn: shakmaty::Color,
+    /// In SAN. Moves of the repertoire which were never played have no games
+    pub moves: Vec<(String, MoveStatistics)>,
+    /// All imported games
+    pub games: u32
+}
+
+/// A wrong move played in exam mode
+#[derive(Clone)]
+pub struct Deviation {
+    /// The position before the move
+    pub position: shakmaty::Chess,
+    pub expected: shakmaty::Move,
+    pub played: shakmaty::Move
+}
+
+pub struct Level {
+    /// Starting at 1
+    pub number: usize,
+    pub count: usize,
+    /// The moves of each line, and whether it is mastered
+    pub lines: Vec<(String, bool)>
+}
+
+/// Everything we remember about the student's training, saved to the storage on every change.
+/// Except for the imported games, the records are kept apart for each side the student plays
+struct Records {
+    storage: std::rc::Rc<dyn Storage>,
+    student: shakmaty::Color,
+    line_ids: Vec<String>,
+    line_weights: Vec<f64>,
+    scheduler: std::cell::RefCell<Scheduler>,
+    coverage: std::cell::RefCell<Coverage>,
+    statistics: std::cell::RefCell<Statistics>,
+    mistakes: std::cell::RefCell<Mistakes>,
+    explorer: std::cell::RefCell<Explorer>
+}
+
+impl Records {
+    fn load(storage: std::rc::Rc<dyn Storage>, student: shakmaty::Color, line_ids: Vec<String>, line_weights: Vec<f64>) -> Self {
+        let scheduler = storage::load(&*storage, &Records::key("schedule", student)).unwrap_or_else(Scheduler::new);
+        let coverage = storage::load(&*storage, &Records::key("coverage", student)).unwrap_or_else(Coverage::new);
+        let statistics = storage::load(&*storage, &Records::key("statistics", student)).unwrap_or_else(Statistics::new);
+        let mut mistakes: Mistakes = storage::load(&*storage, &Records::key("mistakes", student)).unwrap_or_else(Mistakes::new);
+        mistakes.retain(|mistake| line_ids.contains(&mistake.line));
+        let explorer = storage::load(&*storage, "explorer").unwrap_or_else(Explorer::new);
+        Records {
+            storage,
+            student,
+            line_ids,
+            line_weights,
+            scheduler: scheduler.into(),
+            coverage: coverage.into(),
+            statistics: statistics.into(),
+            mistakes: mistakes.into(),
+            explorer: explorer.into()
+        }
+    }
+
+    /// The storage key of the records of the side, e.g. `schedule-black`
+    fn key(name: &str, student: shakmaty::Color) -> String {
+        format!("{}-{}", name, student.fold("white", "black"))
+    }
+
+    fn save<T: serde::Serialize>(&self, name: &str, value: &T) {
+        storage::save(&*self.storage, &Records::key(name, self.student), value);
+    }
+
+    /// Returns how many games were imported, and whether the statistics could be saved
+    fn import_games(&self, pgn: &str) -> (Import, bool) {
+        let mut explorer = self.explorer.borrow_mut();
+        let import = explorer.add_pgn(pgn);
+        let saved = storage::save(&*self.storage, "explorer", &*explorer);
+        (import, saved)
+    }
+
+    /// The statistics of the candidates, or of all moves played from the position if there are none
+    fn explorer_moves(&self, pos: &shakmaty::Chess, candidates: &[shakmaty::Move]) -> Option<ExplorerMoves> {
+        let explorer = self.explorer.borrow();
+        if explorer.games() == 0 {
+            return None;
+        }
+
+        let moves = if candidates.is_empty() {
+            explorer.moves(pos)
+        } else {
+            candidates.iter().map(|m| {
+                let san = shakmaty::san::San::from_move(pos, m).to_string();
+                (san, explorer.get(pos, m).copied().unwrap_or_default())
+            }).collect()
+        };
+
+        Some(ExplorerMoves { turn: shakmaty::Setup::turn(pos), moves, games: explorer.games() })
+    }
+
+    fn record_mistake(&self, mistake: Mistake) {
+        let mut mistakes = self.mistakes.borrow_mut();
+        mistakes.record(mistake);
+        self.save("mistakes", &*mistakes);
+    }
+
+    /// The mistake is off the queue if the student found the move without errors or help
+    fn review_mistake(&self, mistake: &Mistake, performance: Performance) {
+        if performance.errors == 0 && performance.hints == 0 {
+            let mut mistakes = self.mistakes.borrow_mut();
+            mistakes.resolve(mistake);
+            self.save("mistakes", &*mistakes);
+        }
+    }
+
+    /// The next mistake to review after `current`, and the index of its line
+    fn choose_mistake(&self, current: Option<&Mistake>) -> Option<(usize, Mistake)> {
+        let mistakes = self.mistakes.borrow();
+        let mistake = mistakes.choose(current)?;
+        Some((self.index_of(&mistake.line)?, mistake.clone()))
+    }
+
+    fn review_line(&self, id: &str, performance: Performance) {
+        let mut scheduler = self.scheduler.borrow_mut();
+        scheduler.review(id, performance, crate::util::now());
+        self.save("schedule", &*scheduler);
+
+        let mut statistics = self.statistics.borrow_mut();
+        statistics.record(id, &performance);
+        self.save("statistics", &*statistics);
+
+        if performance.errors == 0 && performance.hints == 0 {
+            let mut coverage = self.coverage.borrow_mut();
+            coverage.master(id);
+            self.save("coverage", &*coverage);
+        }
+    }
+
+    /// A line trained from a later position. It is scheduled again, but not mastered, see `Scheduler::review_partial`
+    fn review_partial_line(&self, id: &str, performance: Performance) {
+        let mut scheduler = self.scheduler.borrow_mut();
+        scheduler.review_partial(id, performance, crate::util::now());
+        self.save("schedule", &*scheduler);
+
+        let mut statistics = self.statistics.borrow_mut();
+        statistics.record(id, &performance);
+        self.save("statistics", &*statistics);
+    }
+
+    fn index_of(&self, id: &str) -> Option<usize> {
+        self.line_ids.iter().position(|line_id| line_id == id)
+    }
+
+    fn ids(&self) -> Vec<&str> {
+        self.line_ids.iter().map(|id| id.as_str()).collect()
+    }
+
+    /// The levels and the current level, i.e. the first one which isn't mastered yet
+    fn levels(&self, student: shakmaty::Color) -> (Levels, usize) {
+        let ids = self.ids();
+        let levels = Levels::new(&ids, student);
+        let coverage = self.coverage.borrow();
+        let current = levels.current(|index| coverage.is_mastered(ids[index]));
+
+        (levels, current)
+    }
+
+    /// Index of the line to train after `current`
+    fn choose(&self, mode: Mode, student: shakmaty::Color, current: Option<&str>, rng: &mut dyn rand::RngCore) -> usize {
+        let scheduler = self.scheduler.borrow();
+        let coverage = self.coverage.borrow();
+        let statistics = self.statistics.borrow();
+
+        let selector: Box<dyn LineSelector + '_> = match mode {
+            // Review mode falls back to repetition once there are no mistakes left. In random mode,
+            // the line only decides the first moves if the student starts
+            Mode::Repetition | Mode::Review | Mode::Random => {
+                Box::new(selector::SpacedRepetition { scheduler: &scheduler, now: crate::util::now() })
+            },
+            Mode::Sequential => Box::new(selector::Sequential),
+            Mode::Shuffled => Box::new(selector::Random),
+            Mode::WeakestFirst => Box::new(selector::WeakestFirst(&statistics)),
+            Mode::Weighted => Box::new(selector::Weighted(&self.line_weights)),
+            Mode::Coverage(order) => Box::new(selector::Unmastered { coverage: &coverage, order }),
+            Mode::Levels => {
+                let (levels, level) = self.levels(student);
+                Box::new(selector::CurrentLevel { levels, level, coverage: &coverage })
+            }
+        };
+
+        // A selector may find nothing to pick, e.g. if the current level has no lines
+        let ids = self.ids();
+        selector.choose(&ids, current, rng)
+            .or_else(|| selector::Random.choose(&ids, current, rng))
+            .unwrap()
+    }
+
+    fn show_progress(&self, ui: &impl UI, mode: Mode, student: shakmaty::Color) {
+        if mode == Mode::Review {
+            ui.show_progress(mode, 0, self.mistakes.borrow().len());
+        } else {
+            let mastered = self.coverage.borrow().count(self.ids());
+            ui.show_progress(mode, mastered, self.line_ids.len());
+        }
+
+        if mode == Mode::Levels {
+            let (levels, level) = self.levels(student);
+            let coverage = self.coverage.borrow();
+            let lines = levels.lines(level).iter().map(|&index| {
+                let id = &self.line_ids[index];
+                (format_moves(id), coverage.is_mastered(id))
+            }).collect();
+
+            ui.show_level(Some(Level { number: level + 1, count: levels.len(), lines }));
+        } else {
+            ui.show_level(None);
+        }
+    }
+}
+
+/// The state of the game which is restored after a reload
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedGame {
+    student: char,
+    explore: bool,
+    line: String,
+    mode: Mode,
+    exam: bool,
+    review: Option<Mistake>,
+    start: usize
 }
 
 #[derive(Clone)]
@@ -22,27 +425,102 @@ struct SharedGame(std::rc::Rc<std::cell::RefCell<GameInner>>);
 
 struct GameInner {
     iter: crate::pgn::movetree::VariationIterator<'static>,
-    last_turn: Player,
-    explore: bool
+    student: shakmaty::Color,
+    explore: bool,
+    exam: bool,
+    mode: Mode,
+    /// The ply the line is trained from, usually the start
+    start: usize,
+    /// The mistake whose position is trained in review mode
+    review: Option<Mistake>,
+    performance: Performance,
+    /// The answer arrows of the learning mode were shown, so the attempt doesn't tell what the student remembers
+    guided: bool,
+    deviations: Vec<Deviation>,
+    finished: bool,
+    session: Session,
+    /// Moves played in explore mode after leaving the repertoire, they are not part of `iter`
+    off_book: Vec<shakmaty::Move>,
+    /// The position after the moves off the book
+    off_book_position: shakmaty::Chess,
+    /// Used for all random choices, so that a session can be repeated with the same seed
+    rng: rand::rngs::StdRng
 }
 
 impl GameInner {
-    fn new(variation: &crate::pgn::movetree::Variation<'static>) -> Self {
+    fn new(variation: &crate::pgn::movetree::Variation<'static>, student: shakmaty::Color, rng: rand::rngs::StdRng) -> Self {
+        let mut session = Session::new();
+        session.start_line(&variation.id());
+
         GameInner {
             iter: variation.iter(),
-            last_turn: Player::Trainer, // We want to the trainer to play,
-            explore: false
+            student,
+            explore: false,
+            exam: false,
+            mode: Mode::default(),
+            start: 0,
+            review: None,
+            performance: Default::default(),
+            guided: false,
+            deviations: Vec::new(),
+            finished: false,
+            session,
+            off_book: Vec::new(),
+            off_book_position: Default::default(),
+            rng
         }
     }
+
+    /// Replays the line up to the position the training starts from
+    fn go_to_start(&mut self) {
+        self.iter.reset();
+        self.iter.fast_forward(self.start);
+
+        self.performance = Default::default();
+        self.guided = false;
+        self.deviations.clear();
+        self.finished = false;
+        self.off_book.clear();
+        self.session.start_line(&self.iter.line_id());
+    }
+
+    fn position(&self) -> &shakmaty::Chess {
+        if self.off_book.is_empty() { self.iter.position() } else { &self.off_book_position }
+    }
 }
 
 impl SharedGame {
-    pub fn new(variation: &crate::pgn::movetree::Variation<'static>) -> Self {
-        SharedGame(std::cell::RefCell::new(GameInner::new(variation)).into())
+    pub fn new(variation: &crate::pgn::movetree::Variation<'static>, student: shakmaty::Color, rng: rand::rngs::StdRng) -> Self {
+        SharedGame(std::cell::RefCell::new(GameInner::new(variation, student, rng)).into())
+    }
+
+    pub fn rng(&self) -> std::cell::RefMut<rand::rngs::StdRng> {
+        std::cell::RefMut::map(self.0.borrow_mut(), |inner| &mut inner.rng)
+    }
+
+    pub fn reseed(&self, seed: u64) {
+        use rand::SeedableRng;
+        self.0.borrow_mut().rng = rand::rngs::StdRng::seed_from_u64(seed);
     }
 
     pub fn current_player(&self) -> Player {
-        self.0.borrow().last_turn
+        use shakmaty::Setup;
+        let inner = self.0.borrow();
+        if inner.position().turn() == inner.student {
+            Player::Student
+        } else {
+            Player::Trainer
+        }
+    }
+
+    /// The color the student plays
+    pub fn student(&self) -> shakmaty::Color {
+        self.0.borrow().student
+    }
+
+    pub fn switch_side(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.student = !inner.student;
     }
 
     pub fn is_explore(&self) -> bool {
@@ -50,20 +528,220 @@ impl SharedGame {
     }
 
     pub fn reset(&self) {
+        self.0.borrow_mut().go_to_start();
+    }
+
+    pub fn start_variation(&self, variation: &crate::pgn::movetree::Variation<'static>, start: StartPoint) {
+        let mut inner = self.0.borrow_mut();
+        inner.iter = variation.iter();
+        let student = inner.student;
+        inner.start = start.ply(variation.len(), student, &mut inner.rng);
+        inner.review = None;
+        inner.go_to_start();
+    }
+
+    /// Trains the current line from the ply on
+    pub fn start_at(&self, ply: usize) {
         let mut inner = self.0.borrow_mut();
-        inner.iter.reset();
-        inner.last_turn = Player::Trainer;
+        inner.start = ply;
+        inner.review = None;
+        inner.go_to_start();
+    }
+
+    /// The ply the line is trained from
+    pub fn start(&self) -> usize {
+        self.0.borrow().start
+    }
+
+    pub fn ply(&self) -> usize {
+        self.0.borrow().iter.ply()
     }
 
-    pub fn start_variation(&self, variation: &crate::pgn::movetree::Variation<'static>) {
+    /// Starts the line at the position of the mistake. If the line doesn't reach it, it is started from the beginning
+    pub fn start_review(&self, variation: &crate::pgn::movetree::Variation<'static>, mistake: Mistake) {
         let mut inner = self.0.borrow_mut();
         inner.iter = variation.iter();
-        inner.last_turn = Player::Trainer;
+        inner.start = 0;
+        inner.review = None;
+        loop {
+            if shakmaty::fen::fen(inner.iter.position()) == mistake.fen {
+                inner.start = inner.iter.ply();
+                inner.review = Some(mistake);
+                break;
+            }
+
+            if inner.iter.next().is_none() {
+                break;
+            }
+        }
+
+        inner.go_to_start();
     }
 
+    pub fn review(&self) -> Option<Mistake> {
+        self.0.borrow().review.clone()
+    }
+
+    /// Whether the student answered the position under review
+    pub fn is_reviewed(&self) -> bool {
+        let inner = self.0.borrow();
+        inner.review.is_some() && inner.iter.ply() > inner.start
+    }
+
+    /// Describes a wrong move at the current position, `None` if the time ran out
+    pub fn mistake(&self, expected: &shakmaty::Move, played: Option<&shakmaty::Move>) -> Mistake {
+        let inner = self.0.borrow();
+        let pos = inner.iter.position();
+        Mistake {
+            fen: shakmaty::fen::fen(pos),
+            expected: shakmaty::san::San::from_move(pos, expected).to_string(),
+            played: played.map_or(String::new(), |played| shakmaty::san::San::from_move(pos, played).to_string()),
+            line: inner.iter.line_id()
+        }
+    }
+
+    /// Takes back the last move. Returns false at the start of the line
+    pub fn step_back(&self) -> bool {
+        let mut inner = self.0.borrow_mut();
+        if inner.off_book.pop().is_some() {
+            // Like the iterator, the position is replayed without the last move
+            let mut position = inner.iter.position().clone();
+            for m in &inner.off_book {
+                shakmaty::Position::play_unchecked(&mut position, m);
+            }
+            inner.off_book_position = position;
+            return true;
+        }
+
+        // A line which was finished stays graded, replaying its end doesn't count again
+        inner.iter.back()
+    }
+
+    /// Plays a move which is not in the repertoire, in explore mode or after the end of the line
+    pub fn play_off_book(&self, m: &shakmaty::Move) {
+        let mut inner = self.0.borrow_mut();
+        if inner.off_book.is_empty() {
+            inner.off_book_position = inner.iter.position().clone();
+        }
+
+        shakmaty::Position::play_unchecked(&mut inner.off_book_position, m);
+        inner.off_book.push(m.clone());
+    }
+
+    pub fn is_off_book(&self) -> bool {
+        !self.0.borrow().off_book.is_empty()
+    }
+
+    /// Drops the moves off the book. Returns false if there were none
+    pub fn discard_off_book(&self) -> bool {
+        let mut inner = self.0.borrow_mut();
+        let discarded = !inner.off_book.is_empty();
+        inner.off_book.clear();
+        discarded
+    }
+
+    /// The id of the line played so far (see `Variation::id`), if it left the repertoire
+    pub fn off_book_line(&self) -> Option<String> {
+        let inner = self.0.borrow();
+        if inner.off_book.is_empty() {
+            return None;
+        }
+
+        let line_id = inner.iter.line_id();
+        let mut moves: Vec<String> = line_id.split(' ').take(inner.iter.ply()).map(|m| m.to_string()).collect();
+        let mut position = inner.iter.position().clone();
+        for m in &inner.off_book {
+            moves.push(shakmaty::san::SanPlus::from_move_and_play_unchecked(&mut position, m).to_string());
+        }
+
+        Some(moves.join(" "))
+    }
+
+    /// Follows the variation instead, once the moves off the book were added to the repertoire.
+    /// The position stays the same
+    pub fn continue_in(&self, variation: &crate::pgn::movetree::Variation<'static>) {
+        let mut inner = self.0.borrow_mut();
+        let ply = inner.iter.ply() + inner.off_book.len();
+        inner.iter = variation.iter();
+        inner.iter.fast_forward(ply);
+        inner.off_book.clear();
+    }
+
+    pub fn line_id(&self) -> String {
+        self.0.borrow().iter.line_id()
+    }
+
+    pub fn record_error(&self) {
+        self.0.borrow_mut().performance.errors += 1;
+    }
+
+    /// Called whenever the learning mode shows the student the move to play, see `is_guided`
+    pub fn record_guide(&self) {
+        self.0.borrow_mut().guided = true;
+    }
+
+    pub fn is_guided(&self) -> bool {
+        self.0.borrow().guided
+    }
+
+    /// Called whenever the student was shown the move to play
+    pub fn record_hint(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.performance.hints += 1;
+        inner.session.record_hint();
+    }
+
+    /// Called when the student played a move of the line, `time` is how long it took in milliseconds
+    pub fn record_answer(&self, first_try: bool, time: f64) {
+        self.0.borrow_mut().session.record_move(first_try, time);
+    }
+
+    pub fn session(&self) -> Session {
+        self.0.borrow().session.clone()
+    }
+
+    pub fn record_escalation(&self) {
+        self.0.borrow_mut().performance.escalations += 1;
+    }
+
+    /// Records a wrong move at the current position. In exam mode, the line ends there
+    pub fn record_deviation(&self, expected: shakmaty::Move, played: shakmaty::Move) {
+        let mut inner = self.0.borrow_mut();
+        inner.performance.errors += 1;
+
+        let position = inner.iter.position().clone();
+        inner.deviations.push(Deviation { position, expected, played });
+    }
+
+    pub fn deviations(&self) -> Vec<Deviation> {
+        self.0.borrow().deviations.clone()
+    }
+
+    /// Whether a wrong move was played in exam mode, see `record_deviation`
+    pub fn has_deviated(&self) -> bool {
+        !self.0.borrow().deviations.is_empty()
+    }
+
+    /// Marks the line as finished. Returns how it went, unless it was already finished before
+    pub fn finish(&self) -> Option<Performance> {
+        let mut inner = self.0.borrow_mut();
+        if inner.finished {
+            return None;
+        }
+
+        inner.finished = true;
+        let line = inner.iter.line_id();
+        inner.session.complete_line(&line);
+        Some(inner.performance)
+    }
+
+    /// The next move of the line. There is none once the line was left in explore mode
     pub fn next(&self) -> Option<shakmaty::Move> {
         let mut inner = self.0.borrow_mut();
-        inner.last_turn = inner.last_turn.next();
+        if !inner.off_book.is_empty() {
+            return None;
+        }
+
         inner.iter.next()
     }
 
@@ -71,127 +749,561 @@ impl SharedGame {
         self.0.borrow().iter.peek()
     }
 
-    pub fn peek_all(&self) -> Vec<shakmaty::Move> {
-        self.0.borrow().iter.peek_all()
+    pub fn peek_all_counted(&self) -> Vec<(shakmaty::Move, usize)> {
+        self.0.borrow().iter.peek_all_counted()
     }
 
     pub fn try_switch(&self, m: &shakmaty::Move) -> bool {
         self.0.borrow_mut().iter.try_switch(m)
     }
 
+    /// Picks one of the moves from the current position, by their weights. If all have weight 0,
+    /// each has the same chance, like in `Variation::probability`
+    pub fn choose_weighted(&self) -> Option<shakmaty::Move> {
+        use rand::distributions::Distribution;
+        use rand::seq::SliceRandom;
+        let mut inner = self.0.borrow_mut();
+        let candidates = inner.iter.peek_all_weighted();
+        match rand::distributions::WeightedIndex::new(candidates.iter().map(|(_, weight)| *weight)) {
+            Ok(index) => Some(candidates[index.sample(&mut inner.rng)].0.clone()),
+            Err(_) => candidates.choose(&mut inner.rng).map(|(m, _)| m.clone())
+        }
+    }
+
+    /// Comments on a move at the current position which is in the repertoire
+    pub fn feedback(&self, m: &shakmaty::Move) -> Option<Feedback> {
+        let inner = self.0.borrow();
+        let preferred = inner.iter.preferred()?;
+        if &preferred == m {
+            return None;
+        }
+
+        Some(Feedback::NotPreferred(shakmaty::san::San::from_move(inner.iter.position(), &preferred).to_string()))
+    }
+
+    fn save(&self, storage: &dyn Storage) {
+        let inner = self.0.borrow();
+        storage::save(storage, "game", &SavedGame {
+            student: inner.student.char(),
+            explore: inner.explore,
+            line: inner.iter.line_id(),
+            mode: inner.mode,
+            exam: inner.exam,
+            review: inner.review.clone(),
+            start: inner.start
+        });
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.0.borrow().mode
+    }
+
+    pub fn set_mode(&self, mode: Mode) {
+        self.0.borrow_mut().mode = mode;
+    }
+
     pub fn toggle_explore(&self) {
         let mut inner = self.0.borrow_mut();
         inner.explore = !inner.explore;
     }
 
+    pub fn is_exam(&self) -> bool {
+        self.0.borrow().exam
+    }
+
+    pub fn toggle_exam(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.exam = !inner.exam;
+    }
+
     pub fn position(&self) -> std::cell::Ref<shakmaty::Chess> {
-        std::cell::Ref::map(self.0.borrow(), |inner| inner.iter.position())
+        std::cell::Ref::map(self.0.borrow(), |inner| inner.position())
     }
 }
 
-pub async fn train(ui: impl UI + 'static) {
+/// The repertoire, together with the lines the student added in explore mode (see `UserAction::AddToRepertoire`)
+fn build_repertoire(additions: &[String]) -> crate::pgn::movetree::MoveTree<'static> {
     let mut movetree = crate::pgn::movetree::MoveTree::new();
     movetree.add_pgn(include_str!("../data/stafford.pgn"));
     //movetree.add_pgn(include_str!("../data/kid.pgn"));
 
-    let variations = std::rc::Rc::new(movetree).get_all_variations();
+    for line in additions {
+        movetree.add_owned_pgn(line);
+    }
+
+    movetree
+}
 
-    let mut random = rand::thread_rng();
-    let game = SharedGame::new(&variations.choose(&mut random));
+fn load_records(storage: std::rc::Rc<dyn Storage>, variations: &crate::pgn::movetree::Variations<'static>, student: shakmaty::Color) -> Records {
+    let line_ids = (0..variations.len()).map(|index| variations.get(index).id()).collect();
+    let line_weights = (0..variations.len()).map(|index| variations.get(index).probability()).collect();
+    Records::load(storage, student, line_ids, line_weights)
+}
+
+/// Loads the records again, e.g. for the other side. Imported games which didn't fit into the storage are kept
+fn reload_records(records: &Records, variations: &crate::pgn::movetree::Variations<'static>, student: shakmaty::Color) -> std::rc::Rc<Records> {
+    let reloaded = load_records(records.storage.clone(), variations, student);
+    reloaded.explorer.replace(records.explorer.take());
+    std::rc::Rc::new(reloaded)
+}
+
+pub async fn train(ui: impl UI + 'static, storage: std::rc::Rc<dyn Storage>) {
+    let mut additions: Vec<String> = storage::load(&*storage, "additions").unwrap_or_default();
+    let movetree = build_repertoire(&additions);
+
+    let saved: Option<SavedGame> = storage::load(&*storage, "game");
+
+    // The student plays the side chosen last time, otherwise the side given by the PGN,
+    // or white if it doesn't say
+    let student = saved.as_ref().and_then(|saved| shakmaty::Color::from_char(saved.student))
+        .or_else(|| movetree.orientation())
+        .unwrap_or(shakmaty::Color::White);
+    let mode = saved.as_ref().map_or(Mode::default(), |saved| saved.mode);
+    let mut variations = std::rc::Rc::new(movetree).get_all_variations();
+    let mut records = std::rc::Rc::new(load_records(storage.clone(), &variations, student));
+
+    // A seed given by the student starts a session which can be repeated, so the line from last time
+    // is only continued without it
+    let seed = ui.seed();
+    let continued = saved.as_ref().filter(|_| seed.is_none());
+    let seed = seed.unwrap_or_else(|| rand::random::<u32>() as u64);
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    ui.show_seed(seed);
+
+    // Continue with the line from last time, if it still exists
+    let index = continued.and_then(|saved| records.index_of(&saved.line));
+    let index = index.unwrap_or_else(|| records.choose(mode, student, None, &mut rng));
+
+    let game = SharedGame::new(&variations.get(index), student, rng);
+    game.set_mode(mode);
+    if saved.as_ref().is_some_and(|saved| saved.explore) {
+        game.toggle_explore();
+    }
+    if saved.as_ref().is_some_and(|saved| saved.exam) {
+        game.toggle_exam();
+    }
+    match continued {
+        Some(saved) => match saved.review.clone() {
+            Some(mistake) => game.start_review(&variations.get(index), mistake),
+            None if saved.start > 0 => game.start_at(saved.start),
+            None => {}
+        },
+        None => game.start_variation(&variations.get(index), ui.start_point())
+    }
+    ui.init(&game.position(), game.is_explore(), game.student());
+    ui.set_exam(game.is_exam());
+    records.show_progress(&ui, mode, student);
 
     loop {
-        let training = train_moves(ui.clone(), game.clone());
+        game.save(&*storage);
+
+        let training = train_moves(ui.clone(), game.clone(), records.clone());
         let training = crate::util::spawn_local_cancellable(training);
 
-        match ui.wait_for_user_action().await {
-            UserAction::NextLevel => { game.start_variation(&variations.choose(&mut random)); },
+        let action = ui.wait_for_user_action().await;
+        training.cancel();
+        // The countdown of a cancelled move would stay on screen, e.g. in explore mode
+        ui.show_countdown(None);
+
+        match action {
+            UserAction::NextLevel => start_next(&ui, &game, &records, &variations),
             UserAction::Restart => { game.reset(); },
-            UserAction::ToggleExplore => { game.toggle_explore(); }
+            UserAction::ToggleExplore => {
+                // Explore mode continues from the current position, so there is no need to reinitialize the board,
+                // unless moves which are not in the repertoire are taken back
+                game.toggle_explore();
+                ui.set_explore(game.is_explore());
+                if !game.discard_off_book() {
+                    continue;
+                }
+            },
+            UserAction::TrainFromHere => {
+                if game.is_explore() {
+                    game.toggle_explore();
+                }
+                game.start_at(game.ply());
+            },
+            UserAction::AddToRepertoire => {
+                if let Some(line) = game.off_book_line() {
+                    additions.push(line.clone());
+                    storage::save(&*storage, "additions", &additions);
+
+                    // The new moves end in a new leaf, so the line is found with its id
+                    variations = std::rc::Rc::new(build_repertoire(&additions)).get_all_variations();
+                    records = reload_records(&records, &variations, game.student());
+                    if let Some(index) = records.index_of(&line) {
+                        game.continue_in(&variations.get(index));
+                    }
+                    records.show_progress(&ui, game.mode(), game.student());
+                }
+            },
+            UserAction::ImportGames(pgn) => {
+                let (import, saved) = records.import_games(&pgn);
+                ui.show_import(import, saved);
+                // Explore mode shows the new numbers from the current position
+                continue;
+            },
+            UserAction::CheckOwnGames(pgn, player) => {
+                let games = crate::pgn::games::read_games(&pgn);
+                let checked = crate::own_games::check_games(&variations, game.student(), player.as_deref(), &games.games);
+                for checked in &checked {
+                    if let Departure::Deviated(mistake) = &checked.departure {
+                        records.record_mistake(mistake.clone());
+                    }
+                }
+                records.show_progress(&ui, game.mode(), game.student());
+                ui.show_checked_games(checked);
+                continue;
+            },
+            UserAction::ToggleExam => {
+                game.toggle_exam();
+                game.reset();
+                ui.set_exam(game.is_exam());
+            },
+            UserAction::Reseed(seed) => {
+                game.reseed(seed);
+                ui.show_seed(seed);
+                start_next(&ui, &game, &records, &variations);
+            },
+            UserAction::SwitchSide => {
+                game.switch_side();
+                game.reset();
+                records = reload_records(&records, &variations, game.student());
+                records.show_progress(&ui, game.mode(), game.student());
+            },
+            UserAction::StepBack => {
+                // Outside of explore mode the trainer would replay its move right away,
+                // so we go back to the student's last move instead
+                if game.step_back() && !game.is_explore() && game.current_player() == Player::Trainer {
+                    game.step_back();
+                }
+            },
+            UserAction::StepForward => {
+                if game.next().is_some() {
+                    game.record_hint();
+                }
+            },
+            UserAction::SetMode(mode) => {
+                game.set_mode(mode);
+                records.show_progress(&ui, mode, game.student());
+                start_next(&ui, &game, &records, &variations);
+            }
         }
 
-        training.cancel();
+        ui.init(&game.position(), game.is_explore(), game.student());
     }
 }
 
-async fn train_moves(ui: impl UI, game: SharedGame) {
-    let explore = game.is_explore(); // TODO
+/// Waits for a move of the student until the deadline (see `util::now`), counting down the seconds left.
+/// Returns `None` once the time is up
+async fn get_user_move_until(ui: &impl UI, deadline: f64) -> Option<shakmaty::Move> {
+    // The same move is waited for across the seconds
+    let mut user_move = ui.get_user_move();
+    loop {
+        let left = deadline - crate::util::now();
+        if left <= 0.0 {
+            ui.show_countdown(Some(0));
+            return None;
+        }
+
+        // Wait until the shown number of seconds changes
+        let seconds = (left / 1000.0).ceil();
+        ui.show_countdown(Some(seconds as u32));
+        let until_next = left - (seconds - 1.0) * 1000.0;
+
+        if let Some(m) = crate::util::timeout_mut(&mut user_move, until_next.ceil() as u32).await {
+            return Some(m);
+        }
+    }
+}
+
+/// Starts the line to train after the current one. In review mode, this is the position of the next mistake
+fn start_next(ui: &impl UI, game: &SharedGame, records: &Records, variations: &crate::pgn::movetree::Variations<'static>) {
+    if game.mode() == Mode::Review {
+        if let Some((index, mistake)) = records.choose_mistake(game.review().as_ref()) {
+            game.start_review(&variations.get(index), mistake);
+            return;
+        }
+    }
+
+    let index = records.choose(game.mode(), game.student(), Some(&game.line_id()), &mut *game.rng());
+    game.start_variation(&variations.get(index), ui.start_point());
+}
 
+async fn train_moves(ui: impl UI + 'static, game: SharedGame, records: std::rc::Rc<Records>) {
+    // There are no hints in exam mode
+    let show_hints = || ui.show_hints() && !game.is_exam();
+
+    // The arrows of the learning mode are no hints after wrong moves, which would make the line count as
+    // forgotten. The attempt is not graded at all instead, see `SharedGame::is_guided`
     let ui_trainer_move = |m: shakmaty::Move, hint: Option<shakmaty::Move>| {
         let mut arrows = vec![(&m).into()];
         if let Some(hint) = hint {
-            if ui.show_hints() {
+            if show_hints() {
+                game.record_guide();
                 arrows.push((&hint).into());
             }
         }
         ui.play_move(m, arrows);
     };
 
-    ui.init(&game.position(), game.is_explore());
+    // Arrows left over from explore mode are replaced. If the student has to move now, there is
+    // no trainer move which could carry the hint, so it is shown right away. A mistake under
+    // review has to be answered without it
+    if !game.is_explore() {
+        ui.show_off_book(None);
+        ui.show_explorer(None);
+        let mut arrows = Vec::new();
+        if game.current_player() == Player::Student && show_hints() && game.review().is_none() {
+            if let Some(hint) = game.peek() {
+                game.record_guide();
+                arrows.push((&hint).into());
+            }
+        }
+        ui.update_arrows(arrows);
+        ui.update_labels(Vec::new());
+        ui.update_highlights(Vec::new());
+    }
 
     loop {
-        if explore {
-            let arrows = game.peek_all().iter().map::<crate::components::board::Arrow, _>(|m| m.into()).collect();
-            ui.update_arrows(arrows);
+        if game.is_explore() {
+            // The student chooses the moves for both sides. Each candidate is labelled with the
+            // number of lines following it. Once the repertoire is left, there are no candidates
+            // and the line can be added to the repertoire
+            ui.show_off_book(game.off_book_line().map(|line| format_moves(&line)));
+            let candidates = if game.is_off_book() { Vec::new() } else { game.peek_all_counted() };
+            if let Some(engine) = ui.explore_engine() {
+                show_analysis(&ui, &game, engine, |_, score| Feedback::Evaluation(score));
+            }
+            let moves: Vec<shakmaty::Move> = candidates.iter().map(|(m, _)| m.clone()).collect();
+            let explorer = records.explorer_moves(&game.position(), &moves);
+            ui.show_explorer(explorer.clone());
+            if !candidates.is_empty() {
+                // The more often a candidate was played in the imported games, the thicker its arrow
+                let arrows = match explorer {
+                    Some(explorer) => {
+                        let total: u32 = explorer.moves.iter().map(|(_, statistics)| statistics.games).sum();
+                        moves.iter().zip(&explorer.moves).map(|(m, (_, statistics))| {
+                            let share = if total == 0 { 0.25 } else { statistics.games as f32 / total as f32 };
+                            crate::components::board::Arrow(m.from().unwrap(), m.to(), 0.5 + 2.0 * share)
+                        }).collect()
+                    },
+                    None => moves.iter().map(|m| m.into()).collect()
+                };
+                ui.update_arrows(arrows);
+                game.record_hint();
+                ui.update_labels(candidates.iter().map(|(m, lines)| {
+                    crate::components::board::Label(m.to(), lines.to_string())
+                }).collect());
+            }
 
             loop {
                 let user_move = ui.get_user_move().await;
-                if game.try_switch(&user_move) {
+                if !game.is_off_book() && game.try_switch(&user_move) {
+                    if let Some(next_move) = game.next() {
+                        ui.play_move(next_move, Vec::new());
+                    }
+                    break;
+                } else if shakmaty::Position::is_legal(&*game.position(), &user_move) {
+                    game.play_off_book(&user_move);
+                    ui.play_move(user_move, Vec::new());
                     break;
                 } else {
                     ui.shake();
                 }
             }
-
-            if let Some(next_move) = game.next() {
-                ui.play_move(next_move, Vec::new());
-            }
         } else {
+            // In review mode, only the position of the mistake is trained
             let expected_move = match game.peek() {
-                Some(m) => m,
-                None => break
+                Some(m) if !game.is_reviewed() && !game.has_deviated() => m,
+                _ => {
+                    if let Some(performance) = game.finish() {
+                        match game.review() {
+                            Some(mistake) => records.review_mistake(&mistake, performance),
+                            // Following the arrows proves nothing, the schedule and the coverage stay as they are
+                            None if game.is_guided() => {},
+                            // Only lines played from the beginning count as mastered
+                            None if game.start() > 0 => records.review_partial_line(&game.line_id(), performance),
+                            None => records.review_line(&game.line_id(), performance)
+                        }
+                        records.show_progress(&ui, game.mode(), game.student());
+                        if game.is_exam() {
+                            ui.show_exam_result(game.deviations());
+                        }
+                        ui.show_session(game.session());
+                    }
+
+                    // Only whole lines are played on, not the position of a mistake
+                    if let Some(engine) = ui.engine().filter(|_| game.review().is_none() && !game.has_deviated()) {
+                        play_out(&ui, &game, &*engine).await;
+                    }
+                    break;
+                }
             };
 
             match game.current_player() {
                 Player::Trainer => {
                     crate::util::sleep(150).await;
 
+                    if game.mode() == Mode::Random {
+                        if let Some(reply) = game.choose_weighted() {
+                            if Some(&reply) != game.peek().as_ref() {
+                                game.try_switch(&reply);
+                            }
+                        }
+                    }
+
                     let expected_move = game.next().unwrap();
                     ui_trainer_move(expected_move, game.peek());
                 },
+                Player::Student if game.is_exam() => {
+                    // No feedback until the end of the line: a wrong move stays on the board and
+                    // ends the line, so that the book move isn't given away
+                    let asked = crate::util::now();
+                    let user_move = ui.get_user_move().await;
+                    let correct = user_move == expected_move || game.try_switch(&user_move);
+                    if !correct {
+                        records.record_mistake(game.mistake(&expected_move, Some(&user_move)));
+                        game.record_deviation(expected_move, user_move.clone());
+                    }
+                    game.record_answer(correct, crate::util::now() - asked);
+                    ui.show_session(game.session());
+
+                    if correct {
+                        let played_move = game.next().unwrap();
+                        ui.play_move(played_move, Vec::new());
+                    } else {
+                        game.play_off_book(&user_move);
+                        ui.play_move(user_move, Vec::new());
+                    }
+                },
                 Player::Student => {
+                    let policy = ui.hint_policy();
+                    let limit = ui.time_limit() as f64 * 1000.0;
+                    let asked = crate::util::now();
+                    let mut deadline = asked + limit;
                     let mut errors = 0;
                     loop {
-                        let user_move = ui.get_user_move().await;
-                        if user_move == expected_move {
+                        let user_move = if limit > 0.0 {
+                            get_user_move_until(&ui, deadline).await
+                        } else {
+                            Some(ui.get_user_move().await)
+                        };
+
+                        // Other moves of the repertoire are fine as well, we continue with one of their lines
+                        if let Some(user_move) = user_move.as_ref().filter(|&m| *m == expected_move || game.try_switch(m)) {
+                            ui.show_countdown(None);
+                            ui.show_feedback(game.feedback(user_move));
                             break;
                         } else {
+                            // Running out of time counts as a wrong move, then the clock starts again
+                            if user_move.is_none() {
+                                deadline = crate::util::now() + limit;
+                            }
+
                             ui.shake();
                             errors = errors + 1;
+                            game.record_error();
+                            if errors == 1 {
+                                records.record_mistake(game.mistake(&expected_move, user_move.as_ref()));
+                                if let Some(engine) = ui.engine().filter(|_| show_hints()) {
+                                    show_analysis(&ui, &game, engine, Feedback::EngineMove);
+                                }
+                            }
             
-                            if errors == 3 {
+                            if let Some(stage) = policy.stage_after(errors) {
                                 // wait a small delay for the shake to end
                                 crate::util::sleep(300).await;
-                                ui.update_arrows(vec![(&expected_move).into()]);
+                                game.record_escalation();
+
+                                let from = expected_move.from().unwrap();
+                                match stage {
+                                    HintStage::Piece => ui.update_highlights(vec![from]),
+                                    HintStage::Target => ui.update_highlights(vec![from, expected_move.to()]),
+                                    HintStage::Arrow => {
+                                        ui.update_highlights(Vec::new());
+                                        ui.update_arrows(vec![(&expected_move).into()]);
+                                        game.record_hint();
+                                    }
+                                }
                             }
                         }
                     }
-            
-                    let expected_move = game.next().unwrap();
-                    ui.play_move(expected_move, Vec::new());
+
+                    game.record_answer(errors == 0, crate::util::now() - asked);
+                    ui.show_session(game.session());
+
+                    let played_move = game.next().unwrap();
+                    ui.play_move(played_move, Vec::new());
                 }
             }
         }
     }
 }
 
+/// Continues the game after the end of the line, with the engine playing the trainer's side
+async fn play_out(ui: &impl UI, game: &SharedGame, engine: &dyn Engine) {
+    loop {
+        let m = match game.current_player() {
+            Player::Trainer => {
+                crate::util::sleep(150).await;
+                let analysis = engine.analyse(&game.position());
+                match analysis.await {
+                    Some(analysis) => analysis.best_move,
+                    None => break
+                }
+            },
+            Player::Student => {
+                if shakmaty::Position::is_game_over(&*game.position()) {
+                    break;
+                }
+
+                let m = ui.get_user_move().await;
+                if !shakmaty::Position::is_legal(&*game.position(), &m) {
+                    ui.shake();
+                    continue;
+                }
+                m
+            }
+        };
+
+        game.play_off_book(&m);
+        ui.play_move(m, Vec::new());
+    }
+}
+
+/// Analyses the current position in the background. The result is shown unless the game moved on in the meantime
+fn show_analysis(ui: &(impl UI + 'static), game: &SharedGame, engine: std::rc::Rc<dyn Engine>, feedback: fn(String, Score) -> Feedback) {
+    let pos = game.position().clone();
+    let (ui, game) = (ui.clone(), game.clone());
+    crate::util::spawn_local(async move {
+        let analysis = match engine.analyse(&pos).await {
+            Some(analysis) => analysis,
+            None => return
+        };
+        if shakmaty::fen::fen(&*game.position()) != shakmaty::fen::fen(&pos) {
+            return;
+        }
+
+        use shakmaty::Setup;
+        let score = if pos.turn() == shakmaty::Color::White { analysis.score } else { analysis.score.flip() };
+        let san = shakmaty::san::San::from_move(&pos, &analysis.best_move).to_string();
+        ui.show_feedback(Some(feedback(san, score)));
+    });
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum Player { Student, Trainer }
 
-impl Player {
-    fn next(self) -> Self {
-        match self {
-            Self::Student => Self::Trainer,
-            Self::Trainer => Self::Student
+/// Adds move numbers to a line id, e.g. "1. e4 e5 2. Nf3"
+pub fn format_moves(id: &str) -> String {
+    let moves: Vec<String> = id.split(' ').enumerate().map(|(index, m)| {
+        if index % 2 == 0 {
+            format!("{}. {}", index / 2 + 1, m)
+        } else {
+            m.to_string()
         }
-    }
+    }).collect();
+
+    moves.join(" ")
 }
\ No newline at end of file
@@ -19,56 +19,150 @@ pub fn oneshot<T: 'static>() -> (async_oneshot::Sender<T>, DynFuture<T>) {
     (sender, receiver)
 }
 
-#[wasm_bindgen::prelude::wasm_bindgen]
-extern "C" {
-    fn setTimeout(closure: &wasm_bindgen::prelude::Closure<dyn FnMut()>, time: u32) -> u32;
-    fn clearTimeout(handle: u32);
-}
+#[cfg(target_arch = "wasm32")]
+mod timer {
+    use super::DynFuture;
 
-pub struct Timeout {
-    handle: u32,
-    _cb: wasm_bindgen::prelude::Closure<dyn FnMut()>
-}
+    #[wasm_bindgen::prelude::wasm_bindgen]
+    extern "C" {
+        fn setTimeout(closure: &wasm_bindgen::prelude::Closure<dyn FnMut()>, time: u32) -> u32;
+        fn clearTimeout(handle: u32);
+        #[wasm_bindgen(js_namespace = Date, js_name = now)]
+        fn date_now() -> f64;
+    }
+
+    /// Milliseconds since the epoch
+    pub fn now() -> f64 {
+        date_now()
+    }
+
+    pub struct Timeout {
+        handle: u32,
+        _cb: wasm_bindgen::prelude::Closure<dyn FnMut()>
+    }
 
-impl Timeout {
-    pub fn sleep(time: u32, closure: wasm_bindgen::prelude::Closure<dyn FnMut()>) -> Self {
-        let handle = setTimeout(&closure, time);
-        Timeout {
-            handle,
-            _cb: closure
+    impl Timeout {
+        pub fn sleep(time: u32, closure: wasm_bindgen::prelude::Closure<dyn FnMut()>) -> Self {
+            let handle = setTimeout(&closure, time);
+            Timeout {
+                handle,
+                _cb: closure
+            }
+        }
+    }
+
+    impl Drop for Timeout {
+        fn drop(&mut self) {
+            clearTimeout(self.handle);
         }
     }
+
+    pub fn sleep(time: u32) -> DynFuture<()> {
+        let (mut sender, receiver) = async_oneshot::oneshot();
+        let closure = wasm_bindgen::prelude::Closure::once(move || {
+            let _ = sender.send(());
+        });
+
+        let timeout = Timeout::sleep(time, closure);
+
+        Box::pin(async move {
+            let result = receiver.await;
+            drop(timeout);
+            match result {
+                Ok(result) => {
+                    result
+                },
+                Err(_) => {
+                    let () = std::future::pending().await;
+                    unreachable!();
+                }
+            }
+        })
+    }
+
+    pub fn spawn_local(f: impl std::future::Future<Output=()> + 'static) {
+        wasm_bindgen_futures::spawn_local(f);
+    }
 }
 
-impl Drop for Timeout {
-    fn drop(&mut self) {
-        clearTimeout(self.handle);
+#[cfg(not(target_arch = "wasm32"))]
+mod timer {
+    use super::DynFuture;
+
+    thread_local! {
+        static SPAWNER: std::cell::RefCell<Option<futures::executor::LocalSpawner>> = const { std::cell::RefCell::new(None) };
+    }
+
+    /// Milliseconds since the epoch
+    pub fn now() -> f64 {
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as f64
+    }
+
+    pub fn sleep(time: u32) -> DynFuture<()> {
+        let (mut sender, receiver) = super::oneshot();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(time as u64));
+            let _ = sender.send(());
+        });
+
+        receiver
+    }
+
+    pub fn spawn_local(f: impl std::future::Future<Output=()> + 'static) {
+        use futures::task::LocalSpawnExt;
+        SPAWNER.with(|spawner| {
+            spawner.borrow().as_ref().expect("spawn_local called outside of run_local").spawn_local(f).unwrap();
+        });
+    }
+
+    /// Runs the future on a single threaded executor. Futures spawned with `spawn_local`
+    /// are driven by the same executor, which is the native counterpart to the browser's event loop
+    pub fn run_local<T>(f: impl std::future::Future<Output=T>) -> T {
+        let mut pool = futures::executor::LocalPool::new();
+        SPAWNER.with(|spawner| *spawner.borrow_mut() = Some(pool.spawner()));
+        let result = pool.run_until(f);
+        SPAWNER.with(|spawner| *spawner.borrow_mut() = None);
+
+        result
     }
 }
 
-pub fn sleep(time: u32) -> DynFuture<()> {
-    let (mut sender, receiver) = async_oneshot::oneshot();
-    let closure = wasm_bindgen::prelude::Closure::once(move || {
-        let _ = sender.send(());
-    });
-
-    let timeout = Timeout::sleep(time, closure);
-
-    Box::pin(async move {
-        let result = receiver.await;
-        drop(timeout);
-        match result {
-            Ok(result) => {
-                result
-            },
-            Err(_) => {
-                let () = std::future::pending().await;
-                unreachable!();
-            }
+pub use timer::*;
+
+struct WithTimeout<F> {
+    f: F,
+    timer: DynFuture<()>
+}
+
+impl<F: std::future::Future + Unpin> std::future::Future for WithTimeout<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<F::Output>> {
+        let this = self.get_mut();
+        if let std::task::Poll::Ready(result) = std::pin::Pin::new(&mut this.f).poll(cx) {
+            return std::task::Poll::Ready(Some(result));
         }
+
+        this.timer.as_mut().poll(cx).map(|()| None)
+    }
+}
+
+/// Waits for the future at most `time` milliseconds. Returns `None` if the time ran out
+pub fn timeout<T: 'static>(f: DynFuture<T>, time: u32) -> DynFuture<Option<T>> {
+    Box::pin(WithTimeout {
+        f,
+        timer: sleep(time)
     })
 }
 
+/// Like `timeout`, but the future is kept when the time runs out, so it can be waited for again
+pub fn timeout_mut<T>(f: &mut DynFuture<T>, time: u32) -> impl std::future::Future<Output=Option<T>> + '_ {
+    WithTimeout {
+        f,
+        timer: sleep(time)
+    }
+}
+
 pub struct EventChannel<T> {
     inner: std::rc::Rc<std::cell::RefCell<EventChannelInner<T>>>
 }
@@ -98,7 +192,7 @@ impl<T: Clone + 'static> EventChannel<T> {
         }
     }
 
-    pub fn _send(&self, value: T) {
+    pub fn send(&self, value: T) {
         self.inner.borrow_mut().send(value)
     }
 
@@ -171,7 +265,7 @@ mod spawn {
             cancelled: handle.0.clone()
         };
 
-        wasm_bindgen_futures::spawn_local(f);
+        super::spawn_local(f);
         handle
     }
 }